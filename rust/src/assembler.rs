@@ -0,0 +1,233 @@
+use crate::types::Word;
+use std::collections::HashMap;
+
+/// Mnemonic table, kept in sync with `scripts/assembler.js`
+const OPCODES: &[(&str, u8)] = &[
+    ("STOP", 0x00),
+    ("ADD", 0x01),
+    ("MUL", 0x02),
+    ("SUB", 0x03),
+    ("DIV", 0x04),
+    ("SDIV", 0x05),
+    ("MOD", 0x06),
+    ("SMOD", 0x07),
+    ("ADDMOD", 0x08),
+    ("MULMOD", 0x09),
+    ("EXP", 0x0a),
+    ("SIGNEXTEND", 0x0b),
+    ("LT", 0x10),
+    ("GT", 0x11),
+    ("SLT", 0x12),
+    ("SGT", 0x13),
+    ("EQ", 0x14),
+    ("ISZERO", 0x15),
+    ("AND", 0x16),
+    ("OR", 0x17),
+    ("XOR", 0x18),
+    ("NOT", 0x19),
+    ("BYTE", 0x1a),
+    ("SHL", 0x1b),
+    ("SHR", 0x1c),
+    ("SAR", 0x1d),
+    ("SHA3", 0x20),
+    ("ADDRESS", 0x30),
+    ("BALANCE", 0x31),
+    ("ORIGIN", 0x32),
+    ("CALLER", 0x33),
+    ("CALLVALUE", 0x34),
+    ("CALLDATALOAD", 0x35),
+    ("CALLDATASIZE", 0x36),
+    ("CALLDATACOPY", 0x37),
+    ("CODESIZE", 0x38),
+    ("CODECOPY", 0x39),
+    ("GASPRICE", 0x3a),
+    ("EXTCODESIZE", 0x3b),
+    ("EXTCODECOPY", 0x3c),
+    ("RETURNDATASIZE", 0x3d),
+    ("RETURNDATACOPY", 0x3e),
+    ("EXTCODEHASH", 0x3f),
+    ("BLOCKHASH", 0x40),
+    ("COINBASE", 0x41),
+    ("TIMESTAMP", 0x42),
+    ("NUMBER", 0x43),
+    ("DIFFICULTY", 0x44),
+    ("GASLIMIT", 0x45),
+    ("CHAINID", 0x46),
+    ("SELFBALANCE", 0x47),
+    ("BASEFEE", 0x48),
+    ("BLOBHASH", 0x49),
+    ("BLOBBASEFEE", 0x4a),
+    ("POP", 0x50),
+    ("MLOAD", 0x51),
+    ("MSTORE", 0x52),
+    ("MSTORE8", 0x53),
+    ("SLOAD", 0x54),
+    ("SSTORE", 0x55),
+    ("JUMP", 0x56),
+    ("JUMPI", 0x57),
+    ("PC", 0x58),
+    ("MSIZE", 0x59),
+    ("GAS", 0x5a),
+    ("JUMPDEST", 0x5b),
+    ("PUSH0", 0x5f),
+    ("LOG0", 0xa0),
+    ("LOG1", 0xa1),
+    ("LOG2", 0xa2),
+    ("LOG3", 0xa3),
+    ("LOG4", 0xa4),
+    ("CREATE", 0xf0),
+    ("CALL", 0xf1),
+    ("CALLCODE", 0xf2),
+    ("RETURN", 0xf3),
+    ("DELEGATECALL", 0xf4),
+    ("CREATE2", 0xf5),
+    ("STATICCALL", 0xfa),
+    ("REVERT", 0xfd),
+    ("INVALID", 0xfe),
+    ("SELFDESTRUCT", 0xff),
+];
+
+/// Look up the byte value of a mnemonic (PUSHn/DUPn/SWAPn are derived from their index)
+pub(crate) fn opcode_byte(name: &str) -> Option<u8> {
+    let indexed = |prefix: &str, base: u8, max: u8| -> Option<u8> {
+        let n: u8 = name.strip_prefix(prefix)?.parse().ok()?;
+        if (1..=max).contains(&n) {
+            Some(base + n - 1)
+        } else {
+            None
+        }
+    };
+
+    indexed("PUSH", 0x60, 32)
+        .or_else(|| indexed("DUP", 0x80, 16))
+        .or_else(|| indexed("SWAP", 0x90, 16))
+        .or_else(|| OPCODES.iter().find(|(n, _)| *n == name).map(|&(_, byte)| byte))
+}
+
+/// Parse a PUSH operand given as `0x`-prefixed hex or decimal
+fn parse_value(value: &str) -> Result<Word, String> {
+    let parsed = if let Some(hex) = value.strip_prefix("0x") {
+        Word::from_str_radix(hex, 16).map_err(|e| e.to_string())
+    } else {
+        Word::from_dec_str(value).map_err(|e| e.to_string())
+    };
+    parsed.map_err(|e| format!("Invalid value {}: {}", value, e))
+}
+
+/// A single parsed source line
+enum Item<'a> {
+    Label(&'a str),
+    Instruction { byte: u8, operand: Option<&'a str> },
+}
+
+/// Assemble line-based EVM assembly into bytecode
+///
+/// Each line holds one instruction (`PUSH1 0x01`, `ADD`, `JUMPDEST`, ...).
+/// Everything after `;` is a comment. A line of the form `name:` defines a
+/// label, which can then be used as a PUSH operand to reference its offset.
+///
+/// # Example
+/// ```
+/// use evm::{assembler::assemble, evm, Word};
+///
+/// // Count down from 3 to 0
+/// let code = assemble("
+///     PUSH1 3
+/// loop:
+///     JUMPDEST
+///     PUSH1 1
+///     SWAP1
+///     SUB          ; counter - 1
+///     DUP1
+///     PUSH1 loop
+///     JUMPI
+/// ").unwrap();
+/// assert_eq!(code, vec![0x60, 0x03, 0x5b, 0x60, 0x01, 0x90, 0x03, 0x80, 0x60, 0x02, 0x57]);
+///
+/// let result = evm(code);
+/// assert!(result.success);
+/// assert_eq!(result.stack, vec![Word::zero()]);
+/// ```
+pub fn assemble(source: &str) -> Result<Vec<u8>, String> {
+    let mut items = Vec::new();
+    for (line_number, line) in source.lines().enumerate() {
+        let line = line.split(';').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(label) = line.strip_suffix(':') {
+            items.push(Item::Label(label.trim()));
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let name = parts.next().unwrap_or_default();
+        let byte = opcode_byte(&name.to_uppercase())
+            .ok_or_else(|| format!("Line {}: unknown opcode {}", line_number + 1, name))?;
+        let operand = parts.next();
+        if parts.next().is_some() {
+            return Err(format!("Line {}: too many operands", line_number + 1));
+        }
+
+        let is_push = (0x60..=0x7f).contains(&byte);
+        if is_push && operand.is_none() {
+            return Err(format!("Line {}: missing value for {}", line_number + 1, name));
+        }
+        if !is_push && operand.is_some() {
+            return Err(format!("Line {}: {} takes no operand", line_number + 1, name));
+        }
+
+        items.push(Item::Instruction { byte, operand });
+    }
+
+    // First pass: resolve label offsets
+    let mut labels = HashMap::new();
+    let mut offset = 0;
+    for item in &items {
+        match item {
+            Item::Label(label) => {
+                if labels.insert(*label, offset).is_some() {
+                    return Err(format!("Duplicate label {}", label));
+                }
+            }
+            Item::Instruction { byte, operand } => {
+                offset += 1;
+                if operand.is_some() {
+                    offset += (byte - 0x60 + 1) as usize;
+                }
+            }
+        }
+    }
+
+    // Second pass: emit bytecode
+    let mut code = Vec::with_capacity(offset);
+    for item in &items {
+        let Item::Instruction { byte, operand } = item else {
+            continue;
+        };
+        code.push(*byte);
+
+        if let Some(operand) = operand {
+            let size = (byte - 0x60 + 1) as usize;
+            let value = if operand.starts_with(|c: char| c.is_ascii_digit()) {
+                parse_value(operand)?
+            } else {
+                let target = labels
+                    .get(operand)
+                    .ok_or_else(|| format!("Unknown label {}", operand))?;
+                Word::from(*target)
+            };
+
+            if size < 32 && value >> (size * 8) != Word::zero() {
+                return Err(format!("Value {} is not in range for PUSH{}", operand, size));
+            }
+
+            let mut bytes = [0u8; 32];
+            value.to_big_endian(&mut bytes);
+            code.extend_from_slice(&bytes[32 - size..]);
+        }
+    }
+
+    Ok(code)
+}
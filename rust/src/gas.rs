@@ -1,4 +1,5 @@
 use crate::types::{EvmError, Gas};
+use primitive_types::U256;
 
 /// Gas cost constants for EVM operations
 pub const GAS_BASE: Gas = 2;
@@ -9,10 +10,245 @@ pub const GAS_HIGH: Gas = 10;
 pub const GAS_EXTCODE: Gas = 700;
 pub const GAS_BALANCE: Gas = 400;
 pub const GAS_SLOAD: Gas = 200;
+/// EIP-2929 access costs: the first access to an address or slot is cold, later ones warm
+pub const GAS_WARM_ACCESS: Gas = 100;
+pub const GAS_COLD_SLOAD: Gas = 2100;
+pub const GAS_COLD_ACCOUNT_ACCESS: Gas = 2600;
+pub const GAS_CALL_VALUE: Gas = 9000;
+pub const GAS_NEW_ACCOUNT: Gas = 25000;
+pub const GAS_CALL_STIPEND: Gas = 2300;
 pub const GAS_JUMPDEST: Gas = 1;
 pub const GAS_SSTORE_SET: Gas = 20000;
 pub const GAS_SSTORE_RESET: Gas = 5000;
 pub const GAS_SSTORE_CLEAR: Gas = 15000;
+pub const GAS_CREATE: Gas = 32000;
+pub const GAS_INITCODE_WORD: Gas = 2;
+pub const GAS_EXP_BYTE: Gas = 50;
+pub const GAS_TRANSACTION: Gas = 21000;
+pub const GAS_TX_DATA_ZERO: Gas = 4;
+pub const GAS_TX_DATA_NONZERO: Gas = 16;
+pub const GAS_KECCAK_WORD: Gas = 6;
+pub const GAS_COPY: Gas = 3;
+pub const GAS_MEMORY: Gas = 3;
+pub const GAS_QUAD_COEFF_DIV: Gas = 512;
+
+/// EIP-4844 blob base fee parameters
+pub const MIN_BLOB_BASE_FEE: u64 = 1;
+pub const BLOB_BASE_FEE_UPDATE_FRACTION: u64 = 3338477;
+
+/// Refunds are capped at gas_used / MAX_REFUND_QUOTIENT
+pub const MAX_REFUND_QUOTIENT: Gas = 2;
+
+/// Total cost of a memory of `words` 32-byte words
+pub fn memory_cost(words: usize) -> Gas {
+    let words = words as Gas;
+    GAS_MEMORY * words + words * words / GAS_QUAD_COEFF_DIV
+}
+
+/// Cost of expanding memory from `current_words` to cover `size` bytes at `offset`
+///
+/// Zero-size accesses never expand memory.
+///
+/// # Example
+/// ```
+/// use evm::{evm, gas::memory_expansion_cost};
+///
+/// // PUSH1 32 PUSH2 0x1000 RETURN - expands memory to 129 words
+/// let result = evm(vec![0x60, 0x20, 0x61, 0x10, 0x00, 0xf3]);
+/// assert!(result.success);
+/// assert_eq!(result.return_data, vec![0u8; 32]);
+/// assert_eq!(memory_expansion_cost(0, 0x1000, 32), 3 * 129 + 129 * 129 / 512);
+/// assert_eq!(result.gas_used, 3 + 3 + 2 + memory_expansion_cost(0, 0x1000, 32));
+///
+/// // A zero-size RETURN at the same offset costs nothing extra
+/// let result = evm(vec![0x60, 0x00, 0x61, 0x10, 0x00, 0xf3]);
+/// assert!(result.return_data.is_empty());
+/// assert_eq!(result.gas_used, 3 + 3 + 2);
+///
+/// // CALL pays for the larger of its argument and return regions, here 32 bytes at 0x100
+/// let call = |ret_size: u8| evm(vec![
+///     0x60, ret_size, 0x61, 0x01, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0xff, 0x61, 0xff, 0xff, 0xf1,
+/// ]);
+/// assert_eq!(call(32).gas_used - call(0).gas_used, memory_expansion_cost(0, 0x100, 32));
+/// assert_eq!(call(32).final_memory.len(), 0x120);
+/// ```
+pub fn memory_expansion_cost(current_words: usize, offset: usize, size: usize) -> Gas {
+    if size == 0 {
+        return 0;
+    }
+    let new_words = (offset + size).div_ceil(32);
+    if new_words <= current_words {
+        return 0;
+    }
+    memory_cost(new_words) - memory_cost(current_words)
+}
+
+/// Dynamic cost of EXP: `GAS_EXP_BYTE` per significant byte of the exponent
+///
+/// # Example
+/// ```
+/// use evm::{evm, Word, gas::{exp_cost, GAS_HIGH}};
+///
+/// assert_eq!(exp_cost(Word::zero()), 0);
+/// assert_eq!(exp_cost(Word::from(0xff)), 50);
+/// assert_eq!(exp_cost(Word::from(0x100)), 100);
+/// assert_eq!(exp_cost(Word::max_value()), 50 * 32);
+///
+/// // PUSH2 0x0102 PUSH1 2 EXP - a two-byte exponent
+/// let result = evm(vec![0x61, 0x01, 0x02, 0x60, 0x02, 0x0a]);
+/// assert_eq!(result.gas_used, 3 + 3 + GAS_HIGH + 2 * 50);
+///
+/// // PUSH1 0 PUSH1 2 EXP - a zero exponent only pays the base cost
+/// let result = evm(vec![0x60, 0x00, 0x60, 0x02, 0x0a]);
+/// assert_eq!(result.stack, vec![Word::one()]);
+/// assert_eq!(result.gas_used, 3 + 3 + GAS_HIGH);
+/// ```
+pub fn exp_cost(exponent: U256) -> Gas {
+    GAS_EXP_BYTE * exponent.bits().div_ceil(8) as Gas
+}
+
+/// Cost of a CALL on top of accessing the callee: transferring value, and
+/// creating the callee if the value goes to an empty account
+///
+/// # Example
+/// ```
+/// use evm::gas::call_value_cost;
+///
+/// assert_eq!(call_value_cost(false, true), 0);
+/// assert_eq!(call_value_cost(true, false), 9000);
+/// assert_eq!(call_value_cost(true, true), 9000 + 25000);
+/// ```
+pub fn call_value_cost(transfers_value: bool, new_account: bool) -> Gas {
+    match (transfers_value, new_account) {
+        (false, _) => 0,
+        (true, false) => GAS_CALL_VALUE,
+        (true, true) => GAS_CALL_VALUE + GAS_NEW_ACCOUNT,
+    }
+}
+
+/// Cost of copying `size` bytes into memory, per word, on top of memory expansion
+///
+/// # Example
+/// ```
+/// use evm::{EvmBuilder, EvmError, gas::copy_cost};
+/// use evm::types::Outcome;
+///
+/// assert_eq!(copy_cost(0), 0);
+/// assert_eq!(copy_cost(33), 6);
+///
+/// // PUSH4 0xffffffff PUSH1 0 PUSH1 0 CALLDATACOPY - copying 4 GiB could never be paid for
+/// let code = vec![0x63, 0xff, 0xff, 0xff, 0xff, 0x60, 0x00, 0x60, 0x00, 0x37];
+/// let result = EvmBuilder::new().build().execute(code);
+/// assert_eq!(result.outcome, Outcome::Halted(EvmError::OutOfGas));
+/// ```
+pub fn copy_cost(size: usize) -> Gas {
+    GAS_COPY * size.div_ceil(32) as Gas
+}
+
+/// Cost of the init code of a creation, per word (EIP-3860)
+///
+/// # Example
+/// ```
+/// use evm::{EvmBuilder, Hardfork, gas::initcode_cost};
+///
+/// assert_eq!(initcode_cost(0), 0);
+/// assert_eq!(initcode_cost(33), 4);
+///
+/// // PUSH1 0 PUSH2 0x120 MSTORE - expand memory to 320 bytes up front - then
+/// // PUSH1 0 PUSH2 size PUSH1 0 PUSH1 0 CREATE or CREATE2 from zeroed init code
+/// let create = |hardfork: Hardfork, opcode: u8, size: u8| {
+///     let code = vec![0x60, 0x00, 0x61, 0x01, 0x20, 0x52, 0x60, 0x00, 0x61, 0x00, size, 0x60, 0x00, 0x60, 0x00, opcode];
+///     EvmBuilder::new().hardfork(hardfork).build().execute(code).gas_used
+/// };
+///
+/// // 8 words of init code instead of 1: 7 more words at 2 gas each,
+/// // and CREATE2 hashes them at 6 gas each on top
+/// assert_eq!(create(Hardfork::Shanghai, 0xf0, 0xff) - create(Hardfork::Shanghai, 0xf0, 32), 7 * 2);
+/// assert_eq!(create(Hardfork::Shanghai, 0xf5, 0xff) - create(Hardfork::Shanghai, 0xf5, 32), 7 * (2 + 6));
+/// assert_eq!(create(Hardfork::London, 0xf0, 0xff) - create(Hardfork::London, 0xf0, 32), 0);
+/// ```
+pub fn initcode_cost(size: usize) -> Gas {
+    GAS_INITCODE_WORD * size.div_ceil(32) as Gas
+}
+
+/// Gas every transaction pays before any code runs: the base cost plus its calldata
+///
+/// # Example
+/// ```
+/// use evm::gas::intrinsic_gas;
+///
+/// assert_eq!(intrinsic_gas(&[]), 21000);
+/// assert_eq!(intrinsic_gas(&[0x00, 0x00, 0x01]), 21000 + 4 + 4 + 16);
+/// ```
+pub fn intrinsic_gas(data: &[u8]) -> Gas {
+    data.iter().fold(GAS_TRANSACTION, |gas, &byte| {
+        gas + if byte == 0 { GAS_TX_DATA_ZERO } else { GAS_TX_DATA_NONZERO }
+    })
+}
+
+/// Gas forwarded to a sub-context: all but one 64th of what remains (EIP-150)
+pub fn all_but_one_64th(gas: Gas) -> Gas {
+    gas - gas / 64
+}
+
+/// Approximate `factor * e ** (numerator / denominator)` with a Taylor expansion (EIP-4844)
+///
+/// Saturates at `U256::MAX` once the expansion no longer fits in 256 bits.
+///
+/// # Example
+/// ```
+/// use evm::{EvmBuilder, Word, gas::fake_exponential};
+///
+/// assert_eq!(fake_exponential(1, 0, 3338477), Word::one());
+/// assert_eq!(fake_exponential(1, 1_000_000_000, 3338477), Word::MAX);
+///
+/// let evm = EvmBuilder::new().excess_blob_gas(1_000_000_000).build();
+/// assert_eq!(evm.config().block_blob_base_fee, Word::MAX);
+/// ```
+pub fn fake_exponential(factor: u64, numerator: u64, denominator: u64) -> U256 {
+    let (numerator, denominator) = (U256::from(numerator), U256::from(denominator));
+    let mut output = U256::zero();
+    let mut accumulator = U256::from(factor) * denominator;
+    let mut i = U256::one();
+    while !accumulator.is_zero() {
+        let (Some(sum), Some(product)) = (output.checked_add(accumulator), accumulator.checked_mul(numerator)) else {
+            return U256::MAX;
+        };
+        output = sum;
+        accumulator = product / (denominator * i);
+        i += U256::one();
+    }
+    output / denominator
+}
+
+/// Sum of the static `gas_cost()` of every opcode in `code`
+///
+/// Dynamic costs (memory expansion, storage, calls) and control flow are
+/// ignored, and undefined bytes cost nothing, so this is only a rough estimate.
+///
+/// # Example
+/// ```
+/// use evm::gas::{static_upper_bound, GAS_BASE, GAS_VERY_LOW};
+///
+/// // PUSH1 0x01 PUSH2 0x5b5b ADD POP - the PUSH2 immediate is not decoded as JUMPDESTs
+/// let code = [0x60, 0x01, 0x61, 0x5b, 0x5b, 0x01, 0x50];
+/// assert_eq!(static_upper_bound(&code), 3 * GAS_VERY_LOW + GAS_BASE);
+/// ```
+pub fn static_upper_bound(code: &[u8]) -> Gas {
+    let mut total: Gas = 0;
+    let mut pc = 0;
+    while pc < code.len() {
+        let byte = code[pc];
+        if let Some(opcode) = crate::opcodes::Opcode::from_byte(byte) {
+            total += opcode.gas_cost();
+        }
+        if (0x60..=0x7f).contains(&byte) {
+            pc += (byte - 0x60 + 1) as usize;
+        }
+        pc += 1;
+    }
+    total
+}
 
 /// Gas tracker for EVM execution
 pub struct GasTracker {
@@ -30,6 +266,15 @@ impl GasTracker {
         }
     }
 
+    /// Create a tracker with a pre-seeded refund counter (e.g. when resuming from a checkpoint)
+    pub fn with_refund(gas_limit: Gas, gas_refund: Gas) -> Self {
+        Self {
+            gas_used: 0,
+            gas_limit,
+            gas_refund,
+        }
+    }
+
     /// Consume gas for an operation
     pub fn consume(&mut self, amount: Gas) -> Result<(), EvmError> {
         if self.gas_used + amount > self.gas_limit {
@@ -39,6 +284,48 @@ impl GasTracker {
         Ok(())
     }
 
+    /// Consume all remaining gas, as an invalid or undefined opcode does
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, EvmError};
+    /// use evm::types::Outcome;
+    ///
+    /// // PUSH1 1 INVALID
+    /// let result = EvmBuilder::new().gas_limit(1000).build().execute(vec![0x60, 0x01, 0xfe]);
+    /// assert!(!result.success);
+    /// assert_eq!(result.outcome, Outcome::Halted(EvmError::Invalid));
+    /// assert_eq!((result.gas_used, result.gas_remaining), (1000, 0));
+    ///
+    /// // PUSH1 1 followed by the undefined byte 0x0c
+    /// let result = EvmBuilder::new().gas_limit(1000).build().execute(vec![0x60, 0x01, 0x0c]);
+    /// assert_eq!(result.outcome, Outcome::Halted(EvmError::InvalidOpcode(0x0c)));
+    /// assert_eq!((result.gas_used, result.gas_remaining), (1000, 0));
+    /// ```
+    pub fn consume_all(&mut self) {
+        self.gas_used = self.gas_limit;
+    }
+
+    /// Take back gas a sub-context did not spend
+    ///
+    /// # Example
+    /// ```
+    /// use evm::gas::GasTracker;
+    ///
+    /// // 300 forwarded to a sub-context that only spent 100
+    /// let mut gas = GasTracker::new(1000);
+    /// gas.consume(300).unwrap();
+    /// gas.return_gas(200);
+    /// assert_eq!((gas.gas_used(), gas.remaining()), (100, 900));
+    ///
+    /// // Never below nothing used
+    /// gas.return_gas(500);
+    /// assert_eq!(gas.gas_used(), 0);
+    /// ```
+    pub fn return_gas(&mut self, amount: Gas) {
+        self.gas_used = self.gas_used.saturating_sub(amount);
+    }
+
     /// Get remaining gas
     pub fn remaining(&self) -> Gas {
         self.gas_limit.saturating_sub(self.gas_used)
@@ -54,6 +341,21 @@ impl GasTracker {
         self.gas_limit
     }
 
+    /// Get the accumulated gas refund (uncapped)
+    pub fn gas_refund(&self) -> Gas {
+        self.gas_refund
+    }
+
+    /// Add to the gas refund counter
+    pub fn add_refund(&mut self, amount: Gas) {
+        self.gas_refund += amount;
+    }
+
+    /// Get the refund that will actually be applied, capped against gas used
+    pub fn capped_refund(&self) -> Gas {
+        self.gas_refund.min(self.gas_used / MAX_REFUND_QUOTIENT)
+    }
+
     /// Check if we have enough gas for an operation
     pub fn has_gas(&self, amount: Gas) -> bool {
         self.remaining() >= amount
@@ -8,6 +8,11 @@
 //! - `opcodes`: Opcode definitions and execution framework
 //! - `state`: EVM execution state management
 //! - `vm`: Main VM orchestration
+//! - `assembler`: Mnemonic to bytecode assembler
+//! - `statetest`: Ethereum state test JSON parsing and execution
+//! - `trace`: EIP-3155 execution traces
+//! - `util`: Word arithmetic and address helpers
+//! - `precompiles`: Precompiled contracts reachable through CALL
 
 pub mod types;
 pub mod stack;
@@ -16,9 +21,14 @@ pub mod gas;
 pub mod opcodes;
 pub mod state;
 pub mod vm;
+pub mod assembler;
+pub mod statetest;
+pub mod trace;
+pub mod util;
+pub mod precompiles;
 
 // Re-export main types for convenience
-pub use types::{EvmConfig, EvmResult, EvmError, Address, Word, Gas};
+pub use types::{EvmConfig, EvmResult, EvmError, Address, Word, Gas, Hardfork};
 pub use vm::{Evm, EvmBuilder};
 pub use state::EvmState;
 
@@ -56,25 +66,32 @@ pub fn evm(code: impl AsRef<[u8]>) -> EvmResult {
 /// * `to` - Contract address (or zero for contract creation)
 /// * `from` - Sender address
 /// * `value` - Transaction value
+/// * `gas_price` - Gas price, returned by GASPRICE
 /// 
 /// # Returns
 /// * `EvmResult` - The result of execution including success status, gas used, and return data
 /// 
 /// # Example
 /// ```
-/// use evm::evm_with_tx;
+/// use evm::{evm_with_tx, Word};
 /// 
 /// let code = vec![0x30]; // ADDRESS instruction
 /// let to = [0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xAA];
 /// let from = [0u8; 20];
-/// let value = U256::zero();
-/// let result = evm_with_tx(code, to, from, value);
+/// let value = Word::zero();
+/// let result = evm_with_tx(code, to, from, value, Word::from(10));
+/// assert_eq!(result.stack, vec![Word::from_big_endian(&to)]);
+///
+/// // GASPRICE
+/// let result = evm_with_tx(vec![0x3a], to, from, value, Word::from(10));
+/// assert_eq!(result.stack, vec![Word::from(10)]);
 /// ```
-pub fn evm_with_tx(code: impl AsRef<[u8]>, to: Address, from: Address, value: Word) -> EvmResult {
+pub fn evm_with_tx(code: impl AsRef<[u8]>, to: Address, from: Address, value: Word, gas_price: Word) -> EvmResult {
     let mut config = EvmConfig::default();
     config.transaction.to = to;
     config.transaction.from = from;
     config.transaction.value = value;
+    config.transaction.gas_price = gas_price;
     
     let vm = Evm::new(config);
     vm.execute(code.as_ref().to_vec())
@@ -1,4 +1,4 @@
-/**
+/*
  * EVM From Scratch
  * Rust template
  *
@@ -6,7 +6,8 @@
  *
  * - Install Rust: https://www.rust-lang.org/tools/install
  * - Edit `rust/lib.rs`
- * - Run `cd rust && cargo run` to run the tests
+ * - Run `cd rust && cargo run` to run the tests, or `cargo run -- <file or directory>`
+ *   to run other JSON test files
  *
  * Hint: most people who were trying to learn Rust and EVM at the same
  * gave up and switched to JavaScript, Python, or Go. If you are new
@@ -14,9 +15,12 @@
  */
 
 use evm::types::Block;
+use evm::types::word_from_hex;
+use evm::util::parse_hex;
 use primitive_types::U256;
 use serde::Deserialize;
 use std::cell::RefCell;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 #[derive(Debug, Deserialize)]
@@ -40,57 +44,113 @@ struct Code {
 struct Expect {
     stack: Option<Vec<String>>,
     success: bool,
-    // #[serde(rename = "return")]
-    // ret: Option<String>,
+    #[serde(rename = "return")]
+    ret: Option<String>,
+    logs: Option<Vec<ExpectedLog>>,
+    /// Gas used by the whole execution, for tests about gas accounting
+    gas: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct ExpectedLog {
+    address: String,
+    data: String,
+    topics: Vec<String>,
+}
+
+impl ExpectedLog {
+    fn matches(&self, log: &evm::types::Log) -> bool {
+        let address = word_from_hex(&self.address).unwrap_or_default();
+        let topics: Vec<U256> = self
+            .topics
+            .iter()
+            .map(|topic| word_from_hex(topic).unwrap_or_default())
+            .collect();
+        let data = parse_hex(&self.data).unwrap_or_default();
+
+        address == evm::types::address_to_word(log.address) && topics == log.topics && data == log.data
+    }
+}
+
+/// Parse an address from hex, right-aligning shorter values and keeping the low 20 bytes of longer ones
+fn parse_address(value: &str) -> evm::Address {
+    let bytes = parse_hex(value).unwrap_or_default();
+    let mut word = [0u8; 32];
+    let len = bytes.len().min(32);
+    word[32 - len..].copy_from_slice(&bytes[bytes.len() - len..]);
+    evm::types::word_to_address(U256::from_big_endian(&word))
+}
+
+/// Default test file when no path is given on the command line
+const DEFAULT_TESTS: &str = "../evm.json";
 
 fn main() {
-    let text = std::fs::read_to_string("../evm.json").unwrap();
+    // Accept a test file or a directory of `*.json` test files
+    let path = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_TESTS.to_string());
+    let path = Path::new(&path);
+
+    let files: Vec<PathBuf> = if path.is_dir() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+            .unwrap()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| file.extension().is_some_and(|extension| extension == "json"))
+            .collect();
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut passed = 0;
+    let mut failed = 0;
+    for file in &files {
+        if files.len() > 1 {
+            println!("Running {}", file.display());
+        }
+        let (file_passed, file_failed) = run_file(file);
+        passed += file_passed;
+        failed += file_failed;
+    }
+
+    println!("Passed: {}, failed: {}", passed, failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    println!("Congratulations!");
+}
+
+/// Run every test in a file, returning the number of passed and failed tests
+fn run_file(path: &Path) -> (usize, usize) {
+    let text = std::fs::read_to_string(path).unwrap();
     let data: Vec<Evmtest> = serde_json::from_str(&text).unwrap();
 
     let total = data.len();
+    let mut passed = 0;
+    let mut failed = 0;
 
     for (index, test) in data.iter().enumerate() {
         println!("Test {} of {}: {}", index + 1, total, test.name);
 
-        let code: Vec<u8> = hex::decode(&test.code.bin).unwrap();
+        let code: Vec<u8> = parse_hex(&test.code.bin).unwrap();
 
         // Create EVM configuration from test block data
-        let mut config = evm::EvmConfig::default();
+        let mut config = evm::EvmConfig::test_default();
         
         if let Some(ref block) = test.block {
             // Configure coinbase
             if let Some(ref coinbase_hex) = block.coinbase {
-                let coinbase_clean = coinbase_hex.trim_start_matches("0x");
-                // Pad odd-length hex strings with leading zero
-                let padded_hex = if coinbase_clean.len() % 2 == 1 {
-                    format!("0{}", coinbase_clean)
-                } else {
-                    coinbase_clean.to_string()
-                };
-                let coinbase_bytes = hex::decode(&padded_hex).unwrap_or_default();
-                let mut coinbase = [0u8; 20];
-                
-                // Place the bytes at the end of the 20-byte array (right-aligned)
-                let start_pos = 20 - coinbase_bytes.len();
-                for (i, &byte) in coinbase_bytes.iter().enumerate() {
-                    coinbase[start_pos + i] = byte;
-                }
-                config.coinbase = coinbase;
+                config.coinbase = parse_address(coinbase_hex);
             }
             
             // Configure base fee
             if let Some(ref base_fee_hex) = block.basefee {
-                let base_fee_clean = base_fee_hex.trim_start_matches("0x");
-                let base_fee = U256::from_str_radix(base_fee_clean, 16).unwrap_or_default();
+                let base_fee = word_from_hex(base_fee_hex).unwrap_or_default();
                 config.block_base_fee = base_fee;
             }
             
             // Configure gas limit
             if let Some(ref gas_limit_hex) = block.gaslimit {
-                let gas_limit_clean = gas_limit_hex.trim_start_matches("0x");
-                let gas_limit = U256::from_str_radix(gas_limit_clean, 16).unwrap_or_default();
+                let gas_limit = word_from_hex(gas_limit_hex).unwrap_or_default();
                 config.block_gas_limit = gas_limit;
 
             }
@@ -111,40 +171,42 @@ fn main() {
             
             // Configure chain id
             if let Some(ref chainid_hex) = block.chainid {
-                let chainid_clean = chainid_hex.trim_start_matches("0x");
-                let chainid = U256::from_str_radix(chainid_clean, 16).unwrap_or_default();
+                let chainid = word_from_hex(chainid_hex).unwrap_or_default();
                 config.chain_id = chainid;
             }
             
             // Configure difficulty
             if let Some(ref difficulty_hex) = block.difficulty {
-                let difficulty_clean = difficulty_hex.trim_start_matches("0x");
-                let difficulty = U256::from_str_radix(difficulty_clean, 16).unwrap_or_default();
+                let difficulty = word_from_hex(difficulty_hex).unwrap_or_default();
                 config.block_difficulty = difficulty;
+                // The test suite's difficulty doubles as PREVRANDAO post-Merge
+                config.block_prevrandao = difficulty;
             }
         }
 
         // Parse test transaction if provided
         if let Some(ref test_tx) = test.tx {
             if let Some(ref to_hex) = test_tx.to {
-                let to_clean = to_hex.trim_start_matches("0x");
-                let to_bytes = hex::decode(to_clean).unwrap_or_default();
-                if to_bytes.len() == 20 {
-                    config.transaction.to = to_bytes.try_into().unwrap_or(config.transaction.to);
-                    println!("DEBUG: Setting transaction to address to {}", to_hex);
-                }
+                config.transaction.to = parse_address(to_hex);
+                log::debug!("Setting transaction to address to {}", to_hex);
+            }
+            if let Some(ref origin_hex) = test_tx.origin {
+                config.origin = parse_address(origin_hex);
             }
             if let Some(ref value_hex) = test_tx.value {
-                let value_clean = value_hex.trim_start_matches("0x");
-                let value = U256::from_str_radix(value_clean, 16).unwrap_or_default();
+                let value = word_from_hex(value_hex).unwrap_or_default();
                 config.transaction.value = value;
-                println!("DEBUG: Setting transaction value to {:#X}", value);
+                log::debug!("Setting transaction value to {:#X}", value);
+            }
+            if let Some(ref gas_hex) = test_tx.gas {
+                let gas = word_from_hex(gas_hex).unwrap_or_default();
+                config.gas_limit = gas.try_into().unwrap_or(u64::MAX);
+                log::debug!("Setting transaction gas limit to {}", config.gas_limit);
             }
             if let Some(ref data_hex) = test_tx.data {
-                let data_clean = data_hex.trim_start_matches("0x");
-                let data = hex::decode(data_clean).unwrap_or_default();
+                let data = parse_hex(data_hex).unwrap_or_default();
                 config.transaction.data = data.clone();
-                println!("DEBUG: Setting transaction data to {} bytes", data.len());
+                log::debug!("Setting transaction data to {} bytes", data.len());
             }
         }
 
@@ -160,21 +222,31 @@ fn main() {
         let mut expected_stack: Vec<U256> = Vec::new();
         if let Some(ref stacks) = test.expect.stack {
             for value in stacks {
-                expected_stack.push(U256::from_str_radix(value, 16).unwrap());
+                expected_stack.push(word_from_hex(value).unwrap());
             }
         }
 
-        let mut matching = result.stack.len() == expected_stack.len();
-        if matching {
-            for i in 0..result.stack.len() {
-                if result.stack[i] != expected_stack[i] {
-                    matching = false;
-                    break;
-                }
-            }
-        }
-        
-        matching = matching && result.success == test.expect.success;
+        let expected_return = test
+            .expect
+            .ret
+            .as_ref()
+            .map(|ret| parse_hex(ret).unwrap());
+        let return_matching = expected_return
+            .as_ref()
+            .is_none_or(|ret| *ret == result.return_data);
+        let logs_matching = test.expect.logs.as_ref().is_none_or(|logs| {
+            logs.len() == result.logs.len()
+                && logs.iter().zip(&result.logs).all(|(expected, actual)| expected.matches(actual))
+        });
+
+        let expected_gas = test.expect.gas.as_ref().map(|gas| word_from_hex(gas).unwrap());
+        let gas_matching = expected_gas.is_none_or(|gas| gas == U256::from(result.gas_used));
+
+        let matching = result.stack == expected_stack
+            && result.success == test.expect.success
+            && return_matching
+            && logs_matching
+            && gas_matching;
 
         if !matching {
             println!("Instructions: \n{}\n", test.code.asm);
@@ -193,11 +265,38 @@ fn main() {
             }
             println!("]\n");
 
+            if let Some(ref ret) = expected_return {
+                println!("Expected return: 0x{}", hex::encode(ret));
+                println!("Actual return: 0x{}\n", hex::encode(&result.return_data));
+            }
+
+            if let Some(gas) = expected_gas {
+                println!("Expected gas used: {}", gas);
+                println!("Actual gas used: {}\n", result.gas_used);
+            }
+
+            if let Some(ref logs) = test.expect.logs {
+                println!("Expected logs: [");
+                for log in logs {
+                    println!("  {{ address: {}, topics: {:?}, data: 0x{} }},", log.address, log.topics, log.data.trim_start_matches("0x"));
+                }
+                println!("]\n");
+                println!("Actual logs: [");
+                for log in &result.logs {
+                    let topics: Vec<String> = log.topics.iter().map(|topic| format!("{:#x}", topic)).collect();
+                    println!("  {{ address: 0x{}, topics: {:?}, data: 0x{} }},", hex::encode(log.address), topics, hex::encode(&log.data));
+                }
+                println!("]\n");
+            }
+
             println!("\nHint: {}\n", test.hint);
-            println!("Progress: {}/{}\n\n", index, total);
-            panic!("Test failed");
+            println!("FAIL\n\n");
+            failed += 1;
+            continue;
         }
         println!("PASS");
+        passed += 1;
     }
-    println!("Congratulations!");
+
+    (passed, failed)
 }
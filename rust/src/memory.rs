@@ -6,22 +6,48 @@ pub struct Memory {
     data: Vec<u8>,
     active_words: usize,
     accessed: bool,
+    max_size: usize,
 }
 
+/// Default hard cap on memory size, far above what any realistic gas limit can pay for
+pub const DEFAULT_MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
 impl Memory {
     pub fn new() -> Self {
+        Self::with_limit(DEFAULT_MAX_MEMORY_BYTES)
+    }
+
+    /// Create a memory that refuses to grow beyond `max_size` bytes
+    pub fn with_limit(max_size: usize) -> Self {
         Self {
             data: Vec::new(),
             active_words: 0,
             accessed: false,
+            max_size,
         }
     }
 
     /// Read data from memory
     /// If reading beyond memory bounds, pad with zeros (Ethereum specification)
     /// Also expands memory to accommodate the read operation
+    ///
+    /// A zero-size read touches no memory, whatever its offset.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::memory::Memory;
+    ///
+    /// let mut memory = Memory::new();
+    /// assert!(memory.read(usize::MAX, 0).unwrap().is_empty());
+    /// memory.write(usize::MAX, &[]).unwrap();
+    /// assert_eq!(memory.size(), 0);
+    /// assert!(!memory.has_been_accessed());
+    /// ```
     pub fn read(&mut self, offset: usize, size: usize) -> Result<Vec<u8>, EvmError> {
-        let required_size = offset + size;
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+        let required_size = offset.checked_add(size).ok_or(EvmError::MemoryOutOfBounds)?;
         
         // Mark memory as accessed
         self.accessed = true;
@@ -42,18 +68,41 @@ impl Memory {
             }
         }
         
-        // Update active words if we read beyond current active area
-        let new_active_words = (required_size + 31) / 32; // Round up to nearest word
-        if new_active_words > self.active_words {
-            self.active_words = new_active_words;
-        }
-        
         Ok(result)
     }
 
+    /// Read data without expanding memory, padding with zeros past the end
+    ///
+    /// Meant for tooling; opcodes use `read`, which expands memory as the spec requires.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::memory::Memory;
+    ///
+    /// let mut memory = Memory::new();
+    /// memory.write(0, &[0x01, 0x02]).unwrap();
+    /// assert_eq!(memory.peek(1, 4), vec![0x02, 0, 0, 0]);
+    /// assert_eq!(memory.peek(1000, 2), vec![0, 0]);
+    /// assert_eq!(memory.size(), 32);
+    /// assert_eq!(memory.size_words(), 1);
+    /// ```
+    pub fn peek(&self, offset: usize, size: usize) -> Vec<u8> {
+        let mut result = vec![0u8; size];
+        if offset < self.data.len() {
+            let end = offset.saturating_add(size).min(self.data.len());
+            result[..end - offset].copy_from_slice(&self.data[offset..end]);
+        }
+        result
+    }
+
     /// Write data to memory
+    ///
+    /// Writing no data touches no memory, whatever the offset.
     pub fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), EvmError> {
-        let required_size = offset + data.len();
+        if data.is_empty() {
+            return Ok(());
+        }
+        let required_size = offset.checked_add(data.len()).ok_or(EvmError::MemoryOutOfBounds)?;
         
         // Mark memory as accessed
         self.accessed = true;
@@ -66,26 +115,97 @@ impl Memory {
             self.data[offset + i] = byte;
         }
         
-        // Update active words if we wrote beyond current active area
-        let new_active_words = (required_size + 31) / 32; // Round up to nearest word
-        if new_active_words > self.active_words {
-            self.active_words = new_active_words;
-        }
-        
         Ok(())
     }
 
-    /// Expand memory to accommodate the required size
+    /// Write a word big-endian at `offset`, expanding memory as needed
+    ///
+    /// # Example
+    /// ```
+    /// use evm::memory::Memory;
+    /// use evm::Word;
+    ///
+    /// let mut memory = Memory::new();
+    /// memory.store_word(0, Word::from(0x1122)).unwrap();
+    /// assert_eq!(memory.load_word(0).unwrap(), Word::from(0x1122));
+    /// assert_eq!(memory.size(), 32);
+    ///
+    /// // Overlapping words: the second overwrites the low bytes of the first
+    /// memory.store_word(0, Word::MAX).unwrap();
+    /// memory.store_word(16, Word::zero()).unwrap();
+    /// assert_eq!(memory.load_word(0).unwrap(), Word::MAX << 128);
+    /// assert_eq!(memory.load_word(8).unwrap(), Word::MAX << 192);
+    ///
+    /// // Loading past the end reads zeros and expands memory
+    /// assert_eq!(memory.load_word(40).unwrap(), Word::zero());
+    /// assert_eq!(memory.size_words(), 3);
+    /// ```
+    pub fn store_word(&mut self, offset: usize, value: Word) -> Result<(), EvmError> {
+        let mut data = [0u8; 32];
+        value.to_big_endian(&mut data);
+        self.write(offset, &data)
+    }
+
+    /// Read a big-endian word at `offset`, expanding memory as needed
+    pub fn load_word(&mut self, offset: usize) -> Result<Word, EvmError> {
+        // Reads are zero-padded past the end, so this is always a full word
+        Ok(Word::from_big_endian(&self.read(offset, 32)?))
+    }
+
+    /// Expand memory to accommodate the required size, rounded up to whole words
+    ///
+    /// Fails with `MemoryOutOfBounds` rather than allocating beyond the size limit.
     pub fn expand(&mut self, size: usize) -> Result<(), EvmError> {
-        if size > self.data.len() {
-            self.data.resize(size, 0);
+        if size > self.max_size {
+            return Err(EvmError::MemoryOutOfBounds);
+        }
+        let words = size.div_ceil(32);
+        if words > self.active_words {
+            self.active_words = words;
+            self.data.resize(words * 32, 0);
         }
         Ok(())
     }
 
-    /// Get the current memory size in bytes
+    /// View the whole memory without expanding it
+    ///
+    /// # Example
+    /// ```
+    /// use evm::memory::Memory;
+    ///
+    /// let mut memory = Memory::new();
+    /// memory.write(0, &[0xab; 32]).unwrap();
+    /// assert_eq!(memory.as_slice(), &[0xab; 32]);
+    /// assert_eq!(memory.size(), 32);
+    /// ```
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Get the current memory size in bytes, always a whole number of words
+    ///
+    /// This is the size MSIZE returns and memory expansion gas is paid for.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::memory::Memory;
+    /// use evm::Word;
+    ///
+    /// // A single byte, as MSTORE8 writes, activates a whole word
+    /// let mut memory = Memory::new();
+    /// memory.write(0, &[0xff]).unwrap();
+    /// assert_eq!(memory.size(), 32);
+    ///
+    /// memory.store_word(16, Word::one()).unwrap();
+    /// assert_eq!(memory.size(), 64);
+    ///
+    /// // A read far past the end expands memory to cover it
+    /// memory.load_word(100).unwrap();
+    /// assert_eq!(memory.size(), 160);
+    /// assert_eq!(memory.size_words(), 5);
+    /// ```
     pub fn size(&self) -> usize {
-        self.data.len()
+        self.active_words * 32
     }
 
     /// Get the current memory size in words (32-byte chunks)
@@ -106,6 +226,14 @@ impl Memory {
     pub fn has_been_accessed(&self) -> bool {
         self.accessed
     }
+
+    /// Empty the memory and set a new size limit, keeping the allocation for reuse
+    pub fn reset(&mut self, max_size: usize) {
+        self.data.clear();
+        self.active_words = 0;
+        self.accessed = false;
+        self.max_size = max_size;
+    }
 }
 
 impl Default for Memory {
@@ -1,8 +1,8 @@
-use crate::types::{EvmError, Gas};
-use crate::gas::{GAS_BASE, GAS_VERY_LOW, GAS_LOW, GAS_MID, GAS_HIGH, GAS_EXTCODE, GAS_SLOAD};
+use crate::types::{EvmError, Gas, Hardfork};
+use crate::gas::{GAS_CREATE, GAS_BASE, GAS_VERY_LOW, GAS_LOW, GAS_MID, GAS_HIGH, GAS_SLOAD, GAS_WARM_ACCESS};
 
 /// EVM opcodes
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Opcode {
     // Stop and arithmetic operations
     Stop = 0x00,
@@ -65,6 +65,8 @@ pub enum Opcode {
     Chainid = 0x46,
     Selfbalance = 0x47,
     Basefee = 0x48,
+    Blobhash = 0x49,
+    Blobbasefee = 0x4a,
     
     // Stack, memory, storage and flow operations
     Pop = 0x50,
@@ -167,6 +169,8 @@ pub enum Opcode {
     Create2 = 0xf5,
     Staticcall = 0xfa,
     Revert = 0xfd,
+    /// The designated invalid instruction, unlike bytes that are merely undefined
+    Invalid = 0xfe,
     Selfdestruct = 0xff,
 }
 
@@ -226,6 +230,8 @@ impl Opcode {
             0x46 => Some(Opcode::Chainid),
             0x47 => Some(Opcode::Selfbalance),
             0x48 => Some(Opcode::Basefee),
+            0x49 => Some(Opcode::Blobhash),
+            0x4a => Some(Opcode::Blobbasefee),
             0x50 => Some(Opcode::Pop),
             0x51 => Some(Opcode::Mload),
             0x52 => Some(Opcode::Mstore),
@@ -337,11 +343,289 @@ impl Opcode {
             0xf5 => Some(Opcode::Create2),
             0xfa => Some(Opcode::Staticcall),
             0xfd => Some(Opcode::Revert),
+            0xfe => Some(Opcode::Invalid),
             0xff => Some(Opcode::Selfdestruct),
             _ => None,
         }
     }
 
+    /// Byte value of the opcode, the inverse of `from_byte`
+    ///
+    /// # Example
+    /// ```
+    /// use evm::opcodes::Opcode;
+    ///
+    /// assert_eq!(Opcode::Push1.to_byte(), 0x60);
+    /// assert_eq!(Opcode::Staticcall.to_byte(), 0xfa);
+    ///
+    /// // Every implemented opcode round-trips through its byte
+    /// for byte in 0..=255u8 {
+    ///     if let Some(opcode) = Opcode::from_byte(byte) {
+    ///         assert_eq!(opcode.to_byte(), byte, "{}", opcode.name());
+    ///     }
+    /// }
+    /// ```
+    pub fn to_byte(&self) -> u8 {
+        *self as u8
+    }
+
+    /// Canonical mnemonic, as used by the assembler
+    ///
+    /// # Example
+    /// ```
+    /// use evm::opcodes::Opcode;
+    ///
+    /// assert_eq!(Opcode::Add.name(), "ADD");
+    /// assert_eq!(Opcode::Push1.name(), "PUSH1");
+    /// assert_eq!(Opcode::Push32.name(), "PUSH32");
+    /// assert_eq!(Opcode::Dup16.name(), "DUP16");
+    /// assert_eq!(Opcode::Swap16.name(), "SWAP16");
+    /// assert_eq!(Opcode::Sha3.name(), "SHA3");
+    ///
+    /// // Every name assembles back to its opcode
+    /// for byte in 0..=255u8 {
+    ///     if let Some(opcode) = Opcode::from_byte(byte) {
+    ///         let operand = if (0x60..=0x7f).contains(&byte) { " 0" } else { "" };
+    ///         let source = format!("{}{}", opcode.name(), operand);
+    ///         assert_eq!(evm::assembler::assemble(&source).unwrap()[0], byte);
+    ///     }
+    /// }
+    /// ```
+    pub fn name(&self) -> &'static str {
+        match self {
+            Opcode::Stop => "STOP",
+            Opcode::Add => "ADD",
+            Opcode::Mul => "MUL",
+            Opcode::Sub => "SUB",
+            Opcode::Div => "DIV",
+            Opcode::Sdiv => "SDIV",
+            Opcode::Mod => "MOD",
+            Opcode::Smod => "SMOD",
+            Opcode::Addmod => "ADDMOD",
+            Opcode::Mulmod => "MULMOD",
+            Opcode::Exp => "EXP",
+            Opcode::Signextend => "SIGNEXTEND",
+            Opcode::Lt => "LT",
+            Opcode::Gt => "GT",
+            Opcode::Slt => "SLT",
+            Opcode::Sgt => "SGT",
+            Opcode::Eq => "EQ",
+            Opcode::Iszero => "ISZERO",
+            Opcode::And => "AND",
+            Opcode::Or => "OR",
+            Opcode::Xor => "XOR",
+            Opcode::Not => "NOT",
+            Opcode::Byte => "BYTE",
+            Opcode::Shl => "SHL",
+            Opcode::Shr => "SHR",
+            Opcode::Sar => "SAR",
+            Opcode::Sha3 => "SHA3",
+            Opcode::Address => "ADDRESS",
+            Opcode::Balance => "BALANCE",
+            Opcode::Origin => "ORIGIN",
+            Opcode::Caller => "CALLER",
+            Opcode::Callvalue => "CALLVALUE",
+            Opcode::Calldataload => "CALLDATALOAD",
+            Opcode::Calldatasize => "CALLDATASIZE",
+            Opcode::Calldatacopy => "CALLDATACOPY",
+            Opcode::Codesize => "CODESIZE",
+            Opcode::Codecopy => "CODECOPY",
+            Opcode::Gasprice => "GASPRICE",
+            Opcode::Extcodesize => "EXTCODESIZE",
+            Opcode::Extcodecopy => "EXTCODECOPY",
+            Opcode::Returndatasize => "RETURNDATASIZE",
+            Opcode::Returndatacopy => "RETURNDATACOPY",
+            Opcode::Extcodehash => "EXTCODEHASH",
+            Opcode::Blockhash => "BLOCKHASH",
+            Opcode::Coinbase => "COINBASE",
+            Opcode::Timestamp => "TIMESTAMP",
+            Opcode::Number => "NUMBER",
+            Opcode::Difficulty => "DIFFICULTY",
+            Opcode::Gaslimit => "GASLIMIT",
+            Opcode::Chainid => "CHAINID",
+            Opcode::Selfbalance => "SELFBALANCE",
+            Opcode::Basefee => "BASEFEE",
+            Opcode::Blobhash => "BLOBHASH",
+            Opcode::Blobbasefee => "BLOBBASEFEE",
+            Opcode::Pop => "POP",
+            Opcode::Mload => "MLOAD",
+            Opcode::Mstore => "MSTORE",
+            Opcode::Mstore8 => "MSTORE8",
+            Opcode::Sload => "SLOAD",
+            Opcode::Sstore => "SSTORE",
+            Opcode::Jump => "JUMP",
+            Opcode::Jumpi => "JUMPI",
+            Opcode::Pc => "PC",
+            Opcode::Msize => "MSIZE",
+            Opcode::Gas => "GAS",
+            Opcode::Jumpdest => "JUMPDEST",
+            Opcode::Push0 => "PUSH0",
+            Opcode::Push1 => "PUSH1",
+            Opcode::Push2 => "PUSH2",
+            Opcode::Push3 => "PUSH3",
+            Opcode::Push4 => "PUSH4",
+            Opcode::Push5 => "PUSH5",
+            Opcode::Push6 => "PUSH6",
+            Opcode::Push7 => "PUSH7",
+            Opcode::Push8 => "PUSH8",
+            Opcode::Push9 => "PUSH9",
+            Opcode::Push10 => "PUSH10",
+            Opcode::Push11 => "PUSH11",
+            Opcode::Push12 => "PUSH12",
+            Opcode::Push13 => "PUSH13",
+            Opcode::Push14 => "PUSH14",
+            Opcode::Push15 => "PUSH15",
+            Opcode::Push16 => "PUSH16",
+            Opcode::Push17 => "PUSH17",
+            Opcode::Push18 => "PUSH18",
+            Opcode::Push19 => "PUSH19",
+            Opcode::Push20 => "PUSH20",
+            Opcode::Push21 => "PUSH21",
+            Opcode::Push22 => "PUSH22",
+            Opcode::Push23 => "PUSH23",
+            Opcode::Push24 => "PUSH24",
+            Opcode::Push25 => "PUSH25",
+            Opcode::Push26 => "PUSH26",
+            Opcode::Push27 => "PUSH27",
+            Opcode::Push28 => "PUSH28",
+            Opcode::Push29 => "PUSH29",
+            Opcode::Push30 => "PUSH30",
+            Opcode::Push31 => "PUSH31",
+            Opcode::Push32 => "PUSH32",
+            Opcode::Dup1 => "DUP1",
+            Opcode::Dup2 => "DUP2",
+            Opcode::Dup3 => "DUP3",
+            Opcode::Dup4 => "DUP4",
+            Opcode::Dup5 => "DUP5",
+            Opcode::Dup6 => "DUP6",
+            Opcode::Dup7 => "DUP7",
+            Opcode::Dup8 => "DUP8",
+            Opcode::Dup9 => "DUP9",
+            Opcode::Dup10 => "DUP10",
+            Opcode::Dup11 => "DUP11",
+            Opcode::Dup12 => "DUP12",
+            Opcode::Dup13 => "DUP13",
+            Opcode::Dup14 => "DUP14",
+            Opcode::Dup15 => "DUP15",
+            Opcode::Dup16 => "DUP16",
+            Opcode::Swap1 => "SWAP1",
+            Opcode::Swap2 => "SWAP2",
+            Opcode::Swap3 => "SWAP3",
+            Opcode::Swap4 => "SWAP4",
+            Opcode::Swap5 => "SWAP5",
+            Opcode::Swap6 => "SWAP6",
+            Opcode::Swap7 => "SWAP7",
+            Opcode::Swap8 => "SWAP8",
+            Opcode::Swap9 => "SWAP9",
+            Opcode::Swap10 => "SWAP10",
+            Opcode::Swap11 => "SWAP11",
+            Opcode::Swap12 => "SWAP12",
+            Opcode::Swap13 => "SWAP13",
+            Opcode::Swap14 => "SWAP14",
+            Opcode::Swap15 => "SWAP15",
+            Opcode::Swap16 => "SWAP16",
+            Opcode::Log0 => "LOG0",
+            Opcode::Log1 => "LOG1",
+            Opcode::Log2 => "LOG2",
+            Opcode::Log3 => "LOG3",
+            Opcode::Log4 => "LOG4",
+            Opcode::Create => "CREATE",
+            Opcode::Call => "CALL",
+            Opcode::Callcode => "CALLCODE",
+            Opcode::Return => "RETURN",
+            Opcode::Delegatecall => "DELEGATECALL",
+            Opcode::Create2 => "CREATE2",
+            Opcode::Staticcall => "STATICCALL",
+            Opcode::Revert => "REVERT",
+            Opcode::Invalid => "INVALID",
+            Opcode::Selfdestruct => "SELFDESTRUCT",
+        }
+    }
+
+    /// Number of stack items this opcode pops
+    ///
+    /// # Example
+    /// ```
+    /// use evm::opcodes::Opcode;
+    ///
+    /// assert_eq!((Opcode::Add.stack_inputs(), Opcode::Add.stack_outputs()), (2, 1));
+    /// assert_eq!((Opcode::Push1.stack_inputs(), Opcode::Push1.stack_outputs()), (0, 1));
+    /// assert_eq!((Opcode::Dup1.stack_inputs(), Opcode::Dup1.stack_outputs()), (1, 2));
+    /// assert_eq!((Opcode::Dup16.stack_inputs(), Opcode::Dup16.stack_outputs()), (16, 17));
+    /// assert_eq!((Opcode::Swap1.stack_inputs(), Opcode::Swap1.stack_outputs()), (2, 2));
+    /// assert_eq!((Opcode::Swap16.stack_inputs(), Opcode::Swap16.stack_outputs()), (17, 17));
+    /// assert_eq!((Opcode::Log4.stack_inputs(), Opcode::Log4.stack_outputs()), (6, 0));
+    /// assert_eq!((Opcode::Call.stack_inputs(), Opcode::Call.stack_outputs()), (7, 1));
+    /// assert_eq!((Opcode::Mstore.stack_inputs(), Opcode::Mstore.stack_outputs()), (2, 0));
+    /// assert_eq!((Opcode::Stop.stack_inputs(), Opcode::Stop.stack_outputs()), (0, 0));
+    /// ```
+    pub fn stack_inputs(&self) -> usize {
+        let byte = self.to_byte();
+        match self {
+            Opcode::Dup1 | Opcode::Dup2 | Opcode::Dup3 | Opcode::Dup4 | Opcode::Dup5 | Opcode::Dup6 | Opcode::Dup7 | Opcode::Dup8 |
+            Opcode::Dup9 | Opcode::Dup10 | Opcode::Dup11 | Opcode::Dup12 | Opcode::Dup13 | Opcode::Dup14 | Opcode::Dup15 | Opcode::Dup16 => (byte - 0x80 + 1) as usize,
+            Opcode::Swap1 | Opcode::Swap2 | Opcode::Swap3 | Opcode::Swap4 | Opcode::Swap5 | Opcode::Swap6 | Opcode::Swap7 | Opcode::Swap8 |
+            Opcode::Swap9 | Opcode::Swap10 | Opcode::Swap11 | Opcode::Swap12 | Opcode::Swap13 | Opcode::Swap14 | Opcode::Swap15 | Opcode::Swap16 => (byte - 0x90 + 2) as usize,
+            Opcode::Log0 | Opcode::Log1 | Opcode::Log2 | Opcode::Log3 | Opcode::Log4 => (byte - 0xa0 + 2) as usize,
+            Opcode::Iszero | Opcode::Not | Opcode::Balance | Opcode::Calldataload | Opcode::Extcodesize | Opcode::Extcodehash | Opcode::Blockhash | Opcode::Blobhash | Opcode::Pop | Opcode::Mload | Opcode::Sload | Opcode::Jump | Opcode::Selfdestruct => 1,
+            Opcode::Add | Opcode::Mul | Opcode::Sub | Opcode::Div | Opcode::Sdiv | Opcode::Mod | Opcode::Smod | Opcode::Exp | Opcode::Signextend | Opcode::Lt | Opcode::Gt | Opcode::Slt | Opcode::Sgt | Opcode::Eq | Opcode::And | Opcode::Or | Opcode::Xor | Opcode::Byte | Opcode::Shl | Opcode::Shr | Opcode::Sar | Opcode::Sha3 | Opcode::Mstore | Opcode::Mstore8 | Opcode::Sstore | Opcode::Jumpi | Opcode::Return | Opcode::Revert => 2,
+            Opcode::Addmod | Opcode::Mulmod | Opcode::Calldatacopy | Opcode::Codecopy | Opcode::Returndatacopy | Opcode::Create => 3,
+            Opcode::Extcodecopy | Opcode::Create2 => 4,
+            Opcode::Delegatecall | Opcode::Staticcall => 6,
+            Opcode::Call | Opcode::Callcode => 7,
+            // PUSH, and opcodes that only read the environment
+            _ => 0,
+        }
+    }
+
+    /// Number of stack items this opcode pushes
+    pub fn stack_outputs(&self) -> usize {
+        let byte = self.to_byte();
+        match self {
+            Opcode::Dup1 | Opcode::Dup2 | Opcode::Dup3 | Opcode::Dup4 | Opcode::Dup5 | Opcode::Dup6 | Opcode::Dup7 | Opcode::Dup8 |
+            Opcode::Dup9 | Opcode::Dup10 | Opcode::Dup11 | Opcode::Dup12 | Opcode::Dup13 | Opcode::Dup14 | Opcode::Dup15 | Opcode::Dup16 => (byte - 0x80 + 2) as usize,
+            Opcode::Swap1 | Opcode::Swap2 | Opcode::Swap3 | Opcode::Swap4 | Opcode::Swap5 | Opcode::Swap6 | Opcode::Swap7 | Opcode::Swap8 |
+            Opcode::Swap9 | Opcode::Swap10 | Opcode::Swap11 | Opcode::Swap12 | Opcode::Swap13 | Opcode::Swap14 | Opcode::Swap15 | Opcode::Swap16 => (byte - 0x90 + 2) as usize,
+            Opcode::Log0 | Opcode::Log1 | Opcode::Log2 | Opcode::Log3 | Opcode::Log4 => 0,
+            Opcode::Stop | Opcode::Calldatacopy | Opcode::Codecopy | Opcode::Extcodecopy | Opcode::Returndatacopy | Opcode::Pop | Opcode::Mstore | Opcode::Mstore8 | Opcode::Sstore | Opcode::Jump | Opcode::Jumpi | Opcode::Jumpdest | Opcode::Return | Opcode::Revert | Opcode::Invalid | Opcode::Selfdestruct => 0,
+            _ => 1,
+        }
+    }
+
+    /// Get the hardfork that introduced this opcode
+    pub fn introduced_in(&self) -> Hardfork {
+        match self {
+            Opcode::Delegatecall => Hardfork::Homestead,
+            Opcode::Returndatasize | Opcode::Returndatacopy | Opcode::Staticcall | Opcode::Revert => Hardfork::Byzantium,
+            Opcode::Shl | Opcode::Shr | Opcode::Sar | Opcode::Extcodehash | Opcode::Create2 => Hardfork::Constantinople,
+            Opcode::Chainid | Opcode::Selfbalance => Hardfork::Istanbul,
+            Opcode::Basefee => Hardfork::London,
+            Opcode::Push0 => Hardfork::Shanghai,
+            Opcode::Blobhash | Opcode::Blobbasefee => Hardfork::Cancun,
+            _ => Hardfork::Frontier,
+        }
+    }
+
+    /// Get the number of the EIP that introduced this opcode, if any
+    pub fn eip(&self) -> Option<u32> {
+        match self {
+            Opcode::Delegatecall => Some(7),
+            Opcode::Revert => Some(140),
+            Opcode::Shl | Opcode::Shr | Opcode::Sar => Some(145),
+            Opcode::Returndatasize | Opcode::Returndatacopy => Some(211),
+            Opcode::Staticcall => Some(214),
+            Opcode::Create2 => Some(1014),
+            Opcode::Extcodehash => Some(1052),
+            Opcode::Chainid => Some(1344),
+            Opcode::Selfbalance => Some(1884),
+            Opcode::Basefee => Some(3198),
+            Opcode::Push0 => Some(3855),
+            Opcode::Blobhash => Some(4844),
+            Opcode::Blobbasefee => Some(7516),
+            _ => None,
+        }
+    }
+
     /// Get the gas cost for this opcode
     pub fn gas_cost(&self) -> Gas {
         match self {
@@ -349,24 +633,28 @@ impl Opcode {
             Opcode::Stop => GAS_BASE,
             Opcode::Add | Opcode::Sub | Opcode::Not | Opcode::Lt | Opcode::Gt | Opcode::Slt | Opcode::Sgt | Opcode::Eq | Opcode::Iszero | Opcode::And | Opcode::Or | Opcode::Xor | Opcode::Byte | Opcode::Shl | Opcode::Shr | Opcode::Sar => GAS_VERY_LOW,
             Opcode::Mul | Opcode::Div | Opcode::Sdiv | Opcode::Mod | Opcode::Smod | Opcode::Signextend => GAS_LOW,
-            Opcode::Addmod | Opcode::Mulmod | Opcode::Exp => GAS_MID,
+            Opcode::Addmod | Opcode::Mulmod => GAS_MID,
+            Opcode::Exp => GAS_HIGH,
             
             // SHA3
             Opcode::Sha3 => GAS_MID,
             
             // Environmental information
             Opcode::Address | Opcode::Origin | Opcode::Caller | Opcode::Callvalue | Opcode::Codesize | Opcode::Gasprice | Opcode::Chainid | Opcode::Selfbalance | Opcode::Basefee => GAS_BASE,
-            Opcode::Balance | Opcode::Extcodesize | Opcode::Extcodehash => GAS_EXTCODE,
+            // The cold access surcharge (or pre-Berlin flat price) is added when executed
+            Opcode::Balance | Opcode::Extcodesize | Opcode::Extcodehash => GAS_WARM_ACCESS,
             Opcode::Calldataload | Opcode::Calldatasize | Opcode::Returndatasize => GAS_VERY_LOW,
             Opcode::Calldatacopy | Opcode::Codecopy | Opcode::Extcodecopy | Opcode::Returndatacopy => GAS_VERY_LOW,
             
             // Block information
             Opcode::Blockhash | Opcode::Coinbase | Opcode::Timestamp | Opcode::Number | Opcode::Difficulty | Opcode::Gaslimit => GAS_BASE,
+            Opcode::Blobhash => GAS_VERY_LOW,
+            Opcode::Blobbasefee => GAS_BASE,
             
             // Stack, memory, storage and flow operations
             Opcode::Pop | Opcode::Pc | Opcode::Msize | Opcode::Gas | Opcode::Jumpdest => GAS_BASE,
             Opcode::Mload | Opcode::Mstore | Opcode::Mstore8 => GAS_VERY_LOW,
-            Opcode::Sload => GAS_SLOAD,
+            Opcode::Sload => GAS_WARM_ACCESS,
             Opcode::Sstore => GAS_SLOAD, // Will be calculated dynamically
             Opcode::Jump | Opcode::Jumpi => GAS_MID,
             
@@ -384,8 +672,12 @@ impl Opcode {
             Opcode::Log0 | Opcode::Log1 | Opcode::Log2 | Opcode::Log3 | Opcode::Log4 => GAS_VERY_LOW, // Will be calculated dynamically
             
             // System operations
-            Opcode::Create | Opcode::Call | Opcode::Callcode | Opcode::Delegatecall | Opcode::Create2 | Opcode::Staticcall => GAS_HIGH, // Will be calculated dynamically
+            Opcode::Create | Opcode::Create2 => GAS_CREATE,
+            // Access, value transfer and account creation costs are added when executed
+            Opcode::Call | Opcode::Callcode | Opcode::Delegatecall | Opcode::Staticcall => GAS_WARM_ACCESS,
             Opcode::Return | Opcode::Revert => GAS_BASE,
+            // Consumes all remaining gas when executed
+            Opcode::Invalid => 0,
             Opcode::Selfdestruct => GAS_BASE,
         }
     }
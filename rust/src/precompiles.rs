@@ -0,0 +1,346 @@
+use crate::types::{Address, EvmError, Gas};
+use bn::{AffineG1, AffineG2, Fq, Fq2, Fr, Group, Gt, G1, G2};
+
+/// Fixed cost of ecAdd since Istanbul (EIP-1108)
+pub const GAS_EC_ADD: Gas = 150;
+/// Fixed cost of ecMul since Istanbul (EIP-1108)
+pub const GAS_EC_MUL: Gas = 6000;
+/// Base cost of the ecPairing check since Istanbul (EIP-1108)
+pub const GAS_EC_PAIRING_BASE: Gas = 45000;
+/// Cost of each (G1, G2) pair in the ecPairing check since Istanbul (EIP-1108)
+pub const GAS_EC_PAIRING_PER_PAIR: Gas = 34000;
+
+/// Cost of each BLAKE2 compression round (EIP-152)
+pub const GAS_BLAKE2F_ROUND: Gas = 1;
+
+/// Size in bytes of one (G1, G2) pair in the ecPairing input
+const PAIRING_PAIR_SIZE: usize = 192;
+/// Exact size in bytes of the BLAKE2F input: rounds, h, m, t and the final block flag
+const BLAKE2F_INPUT_SIZE: usize = 213;
+
+/// Gas used and output of a precompile, or the error that made it fail
+pub type PrecompileResult = Result<(Gas, Vec<u8>), EvmError>;
+
+/// A precompile's computation, from input to output
+type Precompile = fn(&[u8]) -> Result<Vec<u8>, EvmError>;
+
+/// Whether `address` is a precompiled contract implemented here
+///
+/// CALLs to these addresses run the precompile instead of account code.
+///
+/// # Example
+/// ```
+/// use evm::{evm, Word};
+///
+/// // Store G = (1, 2) twice, CALL ecAdd with the 128 bytes and read back the 64-byte sum
+/// let code = hex::decode(concat!(
+///     "6001600052", "6002602052", "6001604052", "6002606052",
+///     "60406000608060006000600661fffff1",
+///     "602051", "600051",
+/// )).unwrap();
+/// let result = evm(code);
+///
+/// let word = |hex: &str| Word::from_str_radix(hex, 16).unwrap();
+/// assert_eq!(result.stack, vec![
+///     word("030644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd3"),
+///     word("15ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a5a18a2c4"),
+///     Word::one(),
+/// ]);
+/// ```
+pub fn is_precompile(address: &Address) -> bool {
+    address[..19].iter().all(|&byte| byte == 0) && matches!(address[19], 0x06..=0x09)
+}
+
+/// Run the precompile at `address` with at most `gas_limit` gas
+///
+/// Returns `None` if `address` is not a precompile. A failing precompile
+/// consumes all the gas it was given.
+///
+/// # Example
+/// ```
+/// use evm::precompiles::run;
+///
+/// let mut ec_add = [0u8; 20];
+/// ec_add[19] = 0x06;
+///
+/// // G + G, where G = (1, 2) is the generator
+/// let mut input = [0u8; 128];
+/// input[31] = 1;
+/// input[63] = 2;
+/// input[95] = 1;
+/// input[127] = 2;
+/// let (gas, output) = run(&ec_add, &input, 1000).unwrap().unwrap();
+/// assert_eq!(gas, 150);
+/// assert_eq!(
+///     hex::encode(output),
+///     "030644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd3\
+///      15ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a5a18a2c4"
+/// );
+///
+/// // Not enough gas
+/// assert!(run(&ec_add, &input, 149).unwrap().is_err());
+///
+/// // G * 0x1234
+/// let mut ec_mul = [0u8; 20];
+/// ec_mul[19] = 0x07;
+/// let mut input = [0u8; 96];
+/// input[31] = 1;
+/// input[63] = 2;
+/// input[94..96].copy_from_slice(&[0x12, 0x34]);
+/// let (gas, output) = run(&ec_mul, &input, 6000).unwrap().unwrap();
+/// assert_eq!(gas, 6000);
+/// assert_eq!(
+///     hex::encode(output),
+///     "1eef0e16d998918292d7e7c0164307becd1361baa0149bdcb64430fe27916d82\
+///      0122985db5774524df14987ca279082bfae13bc977328aa2ce0c1795a859a6a9"
+/// );
+///
+/// // An empty pairing check holds
+/// let mut ec_pairing = [0u8; 20];
+/// ec_pairing[19] = 0x08;
+/// let (gas, output) = run(&ec_pairing, &[], 100_000).unwrap().unwrap();
+/// assert_eq!(gas, 45000);
+/// assert_eq!(output, [vec![0u8; 31], vec![1]].concat());
+///
+/// // e(G1, G2) * e(-G1, G2) == 1, but e(G1, G2) * e(G1, G2) != 1
+/// let g2 = "198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2\
+///           1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed\
+///           090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b\
+///           12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa";
+/// let g1 = format!("{:064x}{:064x}", 1, 2);
+/// let minus_g1 = format!("{:064x}30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd45", 1);
+/// let check = |pairs: &[&str]| {
+///     let input = hex::decode(pairs.concat()).unwrap();
+///     run(&ec_pairing, &input, 1_000_000).unwrap().unwrap()
+/// };
+/// assert_eq!(check(&[&g1, g2, &minus_g1, g2]), (45000 + 2 * 34000, [vec![0u8; 31], vec![1]].concat()));
+/// assert_eq!(check(&[&g1, g2, &g1, g2]).1, vec![0u8; 32]);
+///
+/// // The input must be a whole number of pairs
+/// assert!(run(&ec_pairing, &[0u8; 191], 1_000_000).unwrap().is_err());
+///
+/// // BLAKE2F with 12 rounds on the final block of "abc" (EIP-152 test vector 4)
+/// let mut blake2f = [0u8; 20];
+/// blake2f[19] = 0x09;
+/// let mut input = hex::decode(
+///     "0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b\
+///      61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000\
+///      00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000\
+///      0300000000000000000000000000000001"
+/// ).unwrap();
+/// let (gas, output) = run(&blake2f, &input, 12).unwrap().unwrap();
+/// assert_eq!(gas, 12);
+/// assert_eq!(
+///     hex::encode(output),
+///     "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+///      7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+/// );
+///
+/// // The same block, not final (vector 5)
+/// input[212] = 0;
+/// let (_, output) = run(&blake2f, &input, 12).unwrap().unwrap();
+/// assert_eq!(
+///     hex::encode(output),
+///     "75ab69d3190a562c51aef8d88f1c2775876944407270c42c9844252c26d28752\
+///      98743e7f6d5ea2f2d3e8d226039cd31b4e426ac4f2d3d666a610c2116fde4735"
+/// );
+///
+/// // Only 0 and 1 are valid final block flags (vector 3), and the input size is exact (vectors 1 and 2)
+/// input[212] = 2;
+/// assert!(run(&blake2f, &input, 12).unwrap().is_err());
+/// input[212] = 1;
+/// assert!(run(&blake2f, &input[..212], 12).unwrap().is_err());
+/// assert!(run(&blake2f, &[input.clone(), vec![0]].concat(), 12).unwrap().is_err());
+/// assert!(run(&blake2f, &[], 12).unwrap().is_err());
+///
+/// // Not a precompile
+/// assert!(run(&[0xaa; 20], &input, 1000).is_none());
+/// ```
+pub fn run(address: &Address, input: &[u8], gas_limit: Gas) -> Option<PrecompileResult> {
+    if !is_precompile(address) {
+        return None;
+    }
+
+    let (cost, precompile): (Gas, Precompile) = match address[19] {
+        0x06 => (GAS_EC_ADD, ec_add),
+        0x07 => (GAS_EC_MUL, ec_mul),
+        0x08 => {
+            let pairs = (input.len() / PAIRING_PAIR_SIZE) as Gas;
+            (GAS_EC_PAIRING_BASE + GAS_EC_PAIRING_PER_PAIR * pairs, ec_pairing)
+        }
+        0x09 => {
+            // Malformed input fails in blake2f itself, whatever the round count
+            let rounds = input.get(0..4).map_or(0, |bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+            (GAS_BLAKE2F_ROUND * rounds as Gas, blake2f)
+        }
+        _ => unreachable!(),
+    };
+    if cost > gas_limit {
+        return Some(Err(EvmError::OutOfGas));
+    }
+    Some(precompile(input).map(|output| (cost, output)))
+}
+
+/// Input zero-padded (or truncated) to `size` bytes
+fn padded(input: &[u8], size: usize) -> Vec<u8> {
+    let mut padded = input[..input.len().min(size)].to_vec();
+    padded.resize(size, 0);
+    padded
+}
+
+/// Decode a point from 64 bytes of big-endian coordinates; (0, 0) is the point at infinity
+fn read_point(bytes: &[u8]) -> Result<G1, EvmError> {
+    let invalid = || EvmError::Unknown("Invalid alt_bn128 point".to_string());
+    let x = Fq::from_slice(&bytes[0..32]).map_err(|_| invalid())?;
+    let y = Fq::from_slice(&bytes[32..64]).map_err(|_| invalid())?;
+
+    if x.is_zero() && y.is_zero() {
+        return Ok(G1::zero());
+    }
+    AffineG1::new(x, y).map(G1::from).map_err(|_| invalid())
+}
+
+/// Decode a G2 point from 128 bytes: x then y, each as (imaginary, real); zeros are the point at infinity
+fn read_g2_point(bytes: &[u8]) -> Result<G2, EvmError> {
+    let invalid = || EvmError::Unknown("Invalid alt_bn128 G2 point".to_string());
+    let coordinate = |range: std::ops::Range<usize>| Fq::from_slice(&bytes[range]).map_err(|_| invalid());
+    let x = Fq2::new(coordinate(32..64)?, coordinate(0..32)?);
+    let y = Fq2::new(coordinate(96..128)?, coordinate(64..96)?);
+
+    if x.is_zero() && y.is_zero() {
+        return Ok(G2::zero());
+    }
+    AffineG2::new(x, y).map(G2::from).map_err(|_| invalid())
+}
+
+/// Encode a point as 64 bytes of big-endian coordinates
+fn write_point(point: G1) -> Vec<u8> {
+    let mut output = vec![0u8; 64];
+    if let Some(affine) = AffineG1::from_jacobian(point) {
+        // Both buffers are exactly 32 bytes, so encoding cannot fail
+        affine.x().to_big_endian(&mut output[0..32]).unwrap_or_default();
+        affine.y().to_big_endian(&mut output[32..64]).unwrap_or_default();
+    }
+    output
+}
+
+/// ecAdd (0x06): sum of two points
+fn ec_add(input: &[u8]) -> Result<Vec<u8>, EvmError> {
+    let input = padded(input, 128);
+    let a = read_point(&input[0..64])?;
+    let b = read_point(&input[64..128])?;
+    Ok(write_point(a + b))
+}
+
+/// ecMul (0x07): product of a point and a scalar
+fn ec_mul(input: &[u8]) -> Result<Vec<u8>, EvmError> {
+    let input = padded(input, 96);
+    let point = read_point(&input[0..64])?;
+    // Any 256-bit scalar is accepted and reduced modulo the group order
+    let scalar = bn::arith::U256::from_slice(&input[64..96])
+        .map(Fr::new_mul_factor)
+        .map_err(|_| EvmError::Unknown("Invalid alt_bn128 scalar".to_string()))?;
+    Ok(write_point(point * scalar))
+}
+
+/// BLAKE2b message schedule
+const BLAKE2B_SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// BLAKE2b initialization vector
+const BLAKE2B_IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+/// BLAKE2F (0x09): the BLAKE2b compression function F with a given number of rounds (EIP-152)
+fn blake2f(input: &[u8]) -> Result<Vec<u8>, EvmError> {
+    if input.len() != BLAKE2F_INPUT_SIZE {
+        return Err(EvmError::Unknown("Invalid BLAKE2F input length".to_string()));
+    }
+    let final_block = match input[212] {
+        0 => false,
+        1 => true,
+        _ => return Err(EvmError::Unknown("Invalid BLAKE2F final block flag".to_string())),
+    };
+
+    let rounds = u32::from_be_bytes([input[0], input[1], input[2], input[3]]);
+    let word = |offset: usize| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&input[offset..offset + 8]);
+        u64::from_le_bytes(bytes)
+    };
+    let mut h: [u64; 8] = std::array::from_fn(|i| word(4 + 8 * i));
+    let m: [u64; 16] = std::array::from_fn(|i| word(68 + 8 * i));
+    let t = [word(196), word(204)];
+
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(&h);
+    v[8..].copy_from_slice(&BLAKE2B_IV);
+    v[12] ^= t[0];
+    v[13] ^= t[1];
+    if final_block {
+        v[14] = !v[14];
+    }
+
+    let mix = |v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64| {
+        v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+        v[d] = (v[d] ^ v[a]).rotate_right(32);
+        v[c] = v[c].wrapping_add(v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(24);
+        v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+        v[d] = (v[d] ^ v[a]).rotate_right(16);
+        v[c] = v[c].wrapping_add(v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(63);
+    };
+    for round in 0..rounds as usize {
+        let s = &BLAKE2B_SIGMA[round % 10];
+        mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+    Ok(h.iter().flat_map(|word| word.to_le_bytes()).collect())
+}
+
+/// ecPairing (0x08): whether the product of the pairings of all (G1, G2) pairs is one
+fn ec_pairing(input: &[u8]) -> Result<Vec<u8>, EvmError> {
+    if !input.len().is_multiple_of(PAIRING_PAIR_SIZE) {
+        return Err(EvmError::Unknown("Invalid alt_bn128 pairing input length".to_string()));
+    }
+
+    let pairs = input
+        .chunks(PAIRING_PAIR_SIZE)
+        .map(|pair| Ok((read_point(&pair[0..64])?, read_g2_point(&pair[64..192])?)))
+        .collect::<Result<Vec<_>, EvmError>>()?;
+
+    let mut output = vec![0u8; 32];
+    if bn::pairing_batch(&pairs) == Gt::one() {
+        output[31] = 1;
+    }
+    Ok(output)
+}
@@ -31,11 +31,82 @@ impl Stack {
         self.data.pop().ok_or(EvmError::StackUnderflow)
     }
 
+    /// Pop `n` values, top of the stack first
+    ///
+    /// Fails with `StackUnderflow` without popping anything if fewer than `n` values are present.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{stack::Stack, EvmError, Word};
+    ///
+    /// let mut stack = Stack::new();
+    /// stack.push_n(&[Word::from(1), Word::from(2)]).unwrap();
+    ///
+    /// assert!(matches!(stack.pop_n(3), Err(EvmError::StackUnderflow)));
+    /// assert_eq!(stack.data(), &[Word::from(1), Word::from(2)]);
+    ///
+    /// assert_eq!(stack.pop_n(2).unwrap(), vec![Word::from(2), Word::from(1)]);
+    /// assert!(stack.is_empty());
+    /// ```
+    pub fn pop_n(&mut self, n: usize) -> Result<Vec<Word>, EvmError> {
+        if self.data.len() < n {
+            return Err(EvmError::StackUnderflow);
+        }
+        let mut values = self.data.split_off(self.data.len() - n);
+        values.reverse();
+        Ok(values)
+    }
+
+    /// Push several values in order, so the last one ends up on top
+    ///
+    /// Fails with `StackOverflow` without pushing anything if they do not all fit.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{stack::Stack, EvmError, Word};
+    ///
+    /// let mut stack = Stack::new();
+    /// stack.push_n(&vec![Word::zero(); 1000]).unwrap();
+    /// assert!(matches!(stack.push_n(&vec![Word::one(); 25]), Err(EvmError::StackOverflow)));
+    /// assert_eq!(stack.len(), 1000);
+    /// ```
+    pub fn push_n(&mut self, values: &[Word]) -> Result<(), EvmError> {
+        if values.len() > self.remaining_capacity() {
+            return Err(EvmError::StackOverflow);
+        }
+        self.data.extend_from_slice(values);
+        Ok(())
+    }
+
     /// Get the current stack size
     pub fn len(&self) -> usize {
         self.data.len()
     }
 
+    /// Number of values that can still be pushed before the stack overflows
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{stack::Stack, EvmError, Word};
+    ///
+    /// let mut stack = Stack::new();
+    /// assert_eq!(stack.remaining_capacity(), Stack::MAX_SIZE);
+    ///
+    /// // The last free slot at 1023 items can still be filled
+    /// stack.push_n(&vec![Word::zero(); 1023]).unwrap();
+    /// assert_eq!(stack.remaining_capacity(), 1);
+    /// stack.push(Word::one()).unwrap();
+    ///
+    /// // At 1024 items every push fails and leaves the stack as it was
+    /// assert_eq!(stack.remaining_capacity(), 0);
+    /// assert!(matches!(stack.push(Word::one()), Err(EvmError::StackOverflow)));
+    /// assert_eq!(stack.len(), 1024);
+    /// assert_eq!(stack.data()[1023], Word::one());
+    /// ```
+    pub fn remaining_capacity(&self) -> usize {
+        self.max_size - self.data.len()
+    }
+
     /// Check if the stack is empty
     pub fn is_empty(&self) -> bool {
         self.data.is_empty()
@@ -50,6 +121,11 @@ impl Stack {
     pub fn data_mut(&mut self) -> &mut [Word] {
         &mut self.data
     }
+
+    /// Remove all items, keeping the allocation for reuse
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
 }
 
 impl Default for Stack {
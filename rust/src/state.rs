@@ -1,9 +1,25 @@
-use crate::types::{EvmError, EvmConfig, Word, Address};
+use crate::types::{address_to_word, word_to_address, EvmError, EvmConfig, Outcome, Word, Address};
 use primitive_types::U256;
 use crate::stack::Stack;
 use crate::memory::Memory;
 use crate::gas::GasTracker;
-use hex;
+
+/// Number of most recent blocks whose hash BLOCKHASH can return
+const BLOCKHASH_WINDOW: u64 = 256;
+
+/// Largest code a creation may deploy (EIP-170)
+pub const MAX_CODE_SIZE: usize = 24576;
+
+/// Largest init code a creation may run (EIP-3860)
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+/// Where execution continues after an opcode
+enum PcAction {
+    /// The next instruction runs
+    Advance,
+    /// Execution continues at this code position
+    Jump(usize),
+}
 
 /// EVM execution state
 pub struct EvmState {
@@ -12,7 +28,10 @@ pub struct EvmState {
     pub gas_tracker: GasTracker,
     pub program_counter: usize,
     pub code: Vec<u8>,
+    /// Data returned by the last sub-call, read by RETURNDATASIZE/RETURNDATACOPY
     pub return_data: Vec<u8>,
+    /// This context's own output, set only by RETURN or REVERT
+    pub output: Vec<u8>,
     pub logs: Vec<crate::types::Log>,
     
     // Account state (simplified for now)
@@ -34,34 +53,78 @@ pub struct EvmState {
     // Execution flags
     pub halted: bool,
     pub reverted: bool,
-    pub last_jumpi_jumped: bool,
     
-    // Storage for the current contract
-    pub storage: std::collections::HashMap<Word, Word>,
+    // Storage of every account this frame has touched, keyed by account
+    pub storage: std::collections::HashMap<Address, std::collections::HashMap<Word, Word>>,
+    
+    // Storage of the current contract when this frame started, restored if it reverts
+    pub original_storage: std::collections::HashMap<Word, Word>,
+    
+    // Accounts as they were before this frame, or a nested call of it that
+    // succeeded, first changed them in the world state; put back if it reverts
+    pub journal: Vec<(String, Option<crate::types::AccountState>)>,
     
     // Reference to config for dynamic values
     pub config: EvmConfig,
     
     // Static context flag - prevents state modifications in STATICCALL
     pub static_context: bool,
+    
+    /// How execution ended, meaningful once it is no longer running
+    pub outcome: Outcome,
+    
+    /// Gas spent per opcode, `None` unless gas profiling is enabled
+    pub gas_profile: Option<crate::types::GasProfile>,
+    
+    /// Number of opcodes executed so far
+    pub steps: u64,
+    
+    /// Addresses and storage slots already accessed, which are warm (EIP-2929)
+    pub accessed_addresses: std::collections::HashSet<Address>,
+    pub accessed_storage_keys: std::collections::HashSet<(Address, Word)>,
 }
 
 impl EvmState {
     pub fn new(code: Vec<u8>, config: EvmConfig) -> Self {
+        let gas = config.gas_limit;
+        Self::with_gas(code, config, gas)
+    }
+
+    /// Create a state that starts with `gas` instead of the configured gas limit, e.g. for a sub-context
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmState, EvmConfig};
+    ///
+    /// // PUSH1 1 PUSH1 2 ADD
+    /// let mut state = EvmState::with_gas(vec![0x60, 0x01, 0x60, 0x02, 0x01], EvmConfig::default(), 100);
+    /// state.run();
+    /// let result = state.result();
+    /// assert_eq!(result.gas_used, 9);
+    /// assert_eq!(result.gas_remaining, 91);
+    /// assert_eq!(result.gas_used + result.gas_remaining, 100);
+    /// ```
+    pub fn with_gas(code: Vec<u8>, config: EvmConfig, gas: crate::types::Gas) -> Self {
+        let storage = config
+            .test_state
+            .as_ref()
+            .map(|test_state| test_state.borrow().storage(&config.transaction.to))
+            .unwrap_or_default();
         Self {
             stack: Stack::new(),
-            memory: Memory::new(),
-            gas_tracker: GasTracker::new(config.gas_limit),
+            memory: Memory::with_limit(config.max_memory_bytes),
+            gas_tracker: GasTracker::with_refund(gas, config.initial_gas_refund),
             program_counter: 0,
             code,
             return_data: Vec::new(),
+            output: Vec::new(),
             logs: Vec::new(),
             
             // Default account state
             address: config.transaction.to,
             caller: config.transaction.from,
             callvalue: config.transaction.value,
-            origin: [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x13, 0x37],
+            origin: config.origin,
             gas_price: config.transaction.gas_price,
             calldata: config.transaction.data.clone(),
             
@@ -76,53 +139,440 @@ impl EvmState {
             // Execution flags
             halted: false,
             reverted: false,
-            last_jumpi_jumped: false,
             
-            // Initialize storage
-            storage: std::collections::HashMap::new(),
+            // Load the storage of the executing account from the world state
+            storage: std::collections::HashMap::from([(config.transaction.to, storage.clone())]),
+            original_storage: storage,
+            journal: Vec::new(),
+            
+            gas_profile: config.gas_profiling.then(crate::types::GasProfile::new),
+            steps: 0,
+            
+            // The sender, the recipient, precompiles and the access list start warm
+            accessed_addresses: [config.transaction.from, config.transaction.to]
+                .into_iter()
+                .chain((0x06..=0x09).map(|index| {
+                    let mut address = [0u8; 20];
+                    address[19] = index;
+                    address
+                }))
+                .chain(config.access_list.iter().map(|(address, _)| *address))
+                .collect(),
+            static_context: config.static_context,
+            accessed_storage_keys: config
+                .access_list
+                .iter()
+                .flat_map(|(address, keys)| keys.iter().map(move |key| (*address, *key)))
+                .collect(),
             
             // Store config reference
             config,
             
-            // Static context flag - prevents state modifications in STATICCALL
-            static_context: false,
+            // Until RETURN, REVERT or an error says otherwise, execution just stops
+            outcome: Outcome::Stopped,
         }
     }
 
     /// Execute a single step of the EVM
+    ///
+    /// PUSH immediates are skipped as data and never decoded as opcodes.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{evm, Word};
+    ///
+    /// // PUSH1 0x00 PUSH1 0x01 ADD - the 0x00 immediate must not run as STOP
+    /// let result = evm(vec![0x60, 0x00, 0x60, 0x01, 0x01]);
+    /// assert!(result.success);
+    /// assert_eq!(result.stack, vec![Word::from(1)]);
+    /// ```
+    ///
+    /// The stack is checked against the opcode's arity before anything else, so
+    /// an opcode without enough operands fails without charging gas or side effects.
+    ///
+    /// ```
+    /// use evm::{EvmState, EvmConfig, EvmError, Word};
+    ///
+    /// // One operand short of ADD, MSTORE, SSTORE, LOG1 and CALL
+    /// for (operands, opcode) in [(1, 0x01), (1, 0x52), (1, 0x55), (2, 0xa1), (6, 0xf1)] {
+    ///     let mut code = [0x60, 0x01].repeat(operands);
+    ///     code.push(opcode);
+    ///     let mut state = EvmState::new(code, EvmConfig::default());
+    ///     for _ in 0..operands {
+    ///         state.step().unwrap();
+    ///     }
+    ///     let gas = state.gas_tracker.remaining();
+    ///
+    ///     assert_eq!(state.step(), Err(EvmError::StackUnderflow));
+    ///     assert_eq!(state.gas_tracker.remaining(), gas);
+    ///     assert_eq!(state.stack.len(), operands);
+    ///     assert_eq!(state.memory.size(), 0);
+    ///     assert!(state.storage.values().all(|slots| slots.is_empty()));
+    ///     assert!(state.logs.is_empty());
+    /// }
+    ///
+    /// // PUSH1 1, DUP1 and PUSH0 on a full stack, which stays full
+    /// for code in [vec![0x60, 0x01], vec![0x80], vec![0x5f]] {
+    ///     let mut state = EvmState::new(code, EvmConfig::default());
+    ///     state.stack.push_n(&[Word::zero(); 1024]).unwrap();
+    ///     assert_eq!(state.step(), Err(EvmError::StackOverflow));
+    ///     assert_eq!(state.stack.len(), 1024);
+    /// }
+    ///
+    /// // ... but with 1023 items there is room for one more
+    /// let mut state = EvmState::new(vec![0x80], EvmConfig::default());
+    /// state.stack.push_n(&[Word::zero(); 1023]).unwrap();
+    /// state.step().unwrap();
+    /// assert_eq!(state.stack.remaining_capacity(), 0);
+    ///
+    /// // JUMP and a taken JUMPI continue at the destination, a JUMPI not taken
+    /// // at the next instruction:
+    /// // PUSH1 cond PUSH1 6 JUMPI STOP JUMPDEST, and PUSH1 4 JUMP STOP JUMPDEST
+    /// for (condition, pc) in [(1, 6), (0, 5)] {
+    ///     let code = vec![0x60, condition, 0x60, 0x06, 0x57, 0x00, 0x5b];
+    ///     let mut state = EvmState::new(code, EvmConfig::default());
+    ///     for _ in 0..3 {
+    ///         state.step().unwrap();
+    ///     }
+    ///     assert_eq!(state.program_counter, pc);
+    /// }
+    /// let mut state = EvmState::new(vec![0x60, 0x04, 0x56, 0x00, 0x5b], EvmConfig::default());
+    /// state.step().unwrap();
+    /// state.step().unwrap();
+    /// assert_eq!(state.program_counter, 4);
+    /// ```
+    ///
+    /// A PUSH cut off by the end of the code reads its missing operand bytes as zero.
+    ///
+    /// ```
+    /// use evm::{evm, Word};
+    ///
+    /// // A bare PUSH1
+    /// let result = evm(vec![0x60]);
+    /// assert!(result.success);
+    /// assert_eq!(result.stack, vec![Word::zero()]);
+    ///
+    /// // PUSH2 with one of its two operand bytes
+    /// assert_eq!(evm(vec![0x61, 0x01]).stack, vec![Word::from(0x0100)]);
+    /// ```
     pub fn step(&mut self) -> Result<(), EvmError> {
+        self.step_inspect().map(|_| ())
+    }
+
+    /// Execute a single step and return the opcode that ran, or `None` once halted
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmState, EvmConfig, Word};
+    /// use evm::opcodes::Opcode;
+    ///
+    /// // PUSH1 0x01 PUSH1 0x02 ADD
+    /// let mut state = EvmState::new(vec![0x60, 0x01, 0x60, 0x02, 0x01], EvmConfig::default());
+    ///
+    /// assert_eq!(state.step_inspect().unwrap(), Some(Opcode::Push1));
+    /// assert_eq!(state.step_inspect().unwrap(), Some(Opcode::Push1));
+    /// assert_eq!(state.step_inspect().unwrap(), Some(Opcode::Add));
+    /// assert_eq!(state.step_inspect().unwrap(), None);
+    /// assert_eq!(state.stack.data(), &[Word::from(3)]);
+    /// ```
+    pub fn step_inspect(&mut self) -> Result<Option<crate::opcodes::Opcode>, EvmError> {
         if self.halted || self.reverted {
-            return Ok(());
+            return Ok(None);
         }
 
         if self.program_counter >= self.code.len() {
             self.halted = true;
-            return Ok(());
+            return Ok(None);
         }
 
         // Fetch and decode opcode
         let opcode_byte = self.code[self.program_counter];
         let opcode = crate::opcodes::Opcode::from_byte(opcode_byte)
-            .ok_or_else(|| EvmError::InvalidOpcode(opcode_byte))?;
+            .filter(|opcode| self.config.is_opcode_enabled(*opcode))
+            .ok_or(EvmError::InvalidOpcode(opcode_byte))?;
         
+        // Check the stack can supply the operands and hold the results
+        let inputs = opcode.stack_inputs();
+        if self.stack.len() < inputs {
+            return Err(EvmError::StackUnderflow);
+        }
+        if self.stack.len() - inputs + opcode.stack_outputs() > Stack::MAX_SIZE {
+            return Err(EvmError::StackOverflow);
+        }
+
+        if self.config.max_steps.is_some_and(|max_steps| self.steps >= max_steps) {
+            return Err(EvmError::StepLimitExceeded);
+        }
+        self.steps += 1;
+
+        let gas_before = self.gas_tracker.remaining();
+
         // Consume gas for the opcode
         self.gas_tracker.consume(opcode.gas_cost())?;
 
         // Execute the opcode
-        self.execute_opcode(opcode)?;
+        let pc_action = self.execute_opcode(opcode)?;
+
+        if let Some(ref mut profile) = self.gas_profile {
+            let entry = profile.entry(opcode).or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 += gas_before - self.gas_tracker.remaining();
+        }
+
+        match pc_action {
+            PcAction::Advance => self.program_counter += 1,
+            PcAction::Jump(target) => self.program_counter = target,
+        }
+
+        Ok(Some(opcode))
+    }
+
+    /// Start over with new code and config, as `new` would, reusing the buffers
+    /// of the stack, memory, logs and storage instead of allocating fresh ones
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmState, EvmConfig};
+    ///
+    /// // PUSH1 0x2a PUSH1 0 MSTORE PUSH1 1 PUSH1 0 SSTORE PUSH1 0 LOG0
+    /// let code = vec![0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, 0x01, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00, 0xa0];
+    ///
+    /// let mut fresh = EvmState::new(code.clone(), EvmConfig::default());
+    /// fresh.run();
+    ///
+    /// // PUSH1 1 PUSH1 0 MSTORE8 PUSH1 0 - leaves stack, memory and storage behind
+    /// let mut reused = EvmState::new(vec![0x60, 0x01, 0x60, 0x00, 0x53, 0x60, 0x00], EvmConfig::default());
+    /// reused.run();
+    /// reused.reset(code, EvmConfig::default());
+    /// reused.run();
+    ///
+    /// assert_eq!(reused.storage, fresh.storage);
+    /// let (reused, fresh) = (reused.result(), fresh.result());
+    /// assert_eq!(reused.stack, fresh.stack);
+    /// assert_eq!(reused.final_memory, fresh.final_memory);
+    /// assert_eq!(reused.logs.len(), fresh.logs.len());
+    /// assert_eq!(reused.gas_used, fresh.gas_used);
+    /// ```
+    pub fn reset(&mut self, code: Vec<u8>, config: EvmConfig) {
+        let mut stack = std::mem::take(&mut self.stack);
+        stack.clear();
+        let mut memory = std::mem::take(&mut self.memory);
+        memory.reset(config.max_memory_bytes);
+        let mut logs = std::mem::take(&mut self.logs);
+        logs.clear();
+        let mut storage = std::mem::take(&mut self.storage);
+        storage.clear();
+
+        *self = Self::new(code, config);
+        storage.extend(self.storage.drain());
+        self.stack = stack;
+        self.memory = memory;
+        self.logs = logs;
+        self.storage = storage;
+    }
 
-        // Increment program counter (unless opcode modified it)
-        // Note: JUMPI might not actually jump if condition is 0
-        if !self.is_jump_opcode(opcode) || 
-           (opcode == crate::opcodes::Opcode::Jumpi && !self.last_jumpi_jumped) {
-            self.program_counter += 1;
+    /// Run until the execution halts, reverts or fails
+    ///
+    /// An error aborts execution, see `abort`.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmState, EvmConfig, Word};
+    ///
+    /// // PUSH1 5 PUSH1 0 SSTORE PUSH1 0 PUSH1 0 REVERT - the write is rolled back
+    /// let mut state = EvmState::new(vec![0x60, 0x05, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00, 0xfd], EvmConfig::default());
+    /// state.run();
+    /// assert!(!state.result().success);
+    /// assert_eq!(state.storage_value(Word::zero()), Word::zero());
+    /// ```
+    pub fn run(&mut self) {
+        while self.status() == ExecutionStatus::Running {
+            if let Err(error) = self.step() {
+                self.abort(error);
+                break;
+            }
+        }
+    }
+
+    /// Charge the cost of accessing an account beyond the warm price and mark it warm
+    ///
+    /// Before Berlin (EIP-2929) the flat `GAS_EXTCODE` price applies instead.
+    fn charge_account_access(&mut self, address: Address) -> Result<(), EvmError> {
+        let surcharge = if !self.config.is_eip_enabled(2929, crate::types::Hardfork::Berlin) {
+            crate::gas::GAS_EXTCODE - crate::gas::GAS_WARM_ACCESS
+        } else if self.accessed_addresses.insert(address) {
+            crate::gas::GAS_COLD_ACCOUNT_ACCESS - crate::gas::GAS_WARM_ACCESS
+        } else {
+            0
+        };
+        self.gas_tracker.consume(surcharge)
+    }
+
+    /// Charge the cost of accessing a storage slot of the current contract beyond
+    /// the warm price and mark it warm
+    ///
+    /// Before Berlin (EIP-2929) the flat `GAS_SLOAD` price applies instead.
+    fn charge_storage_access(&mut self, key: Word) -> Result<(), EvmError> {
+        let surcharge = if !self.config.is_eip_enabled(2929, crate::types::Hardfork::Berlin) {
+            crate::gas::GAS_SLOAD - crate::gas::GAS_WARM_ACCESS
+        } else if self.accessed_storage_keys.insert((self.address, key)) {
+            crate::gas::GAS_COLD_SLOAD - crate::gas::GAS_WARM_ACCESS
+        } else {
+            0
+        };
+        self.gas_tracker.consume(surcharge)
+    }
+
+    /// Read a storage slot of the current contract, zero if it was never written
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmState, EvmConfig, Word};
+    ///
+    /// let mut state = EvmState::new(vec![], EvmConfig::default());
+    /// assert_eq!(state.storage_value(Word::zero()), Word::zero());
+    ///
+    /// // Slot 0 of another account is not slot 0 of the current contract
+    /// state.storage.entry([0xaa; 20]).or_default().insert(Word::zero(), Word::one());
+    /// assert_eq!(state.storage_value(Word::zero()), Word::zero());
+    /// ```
+    pub fn storage_value(&self, key: Word) -> Word {
+        self.storage
+            .get(&self.address)
+            .and_then(|slots| slots.get(&key))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Remember an account as it is before this frame changes it in the world state
+    fn journal_account(&mut self, address: Address) {
+        if let Some(ref world) = self.config.test_state {
+            let key = crate::types::TestState::account_key(&address);
+            let account = world.borrow().accounts.get(&key).cloned();
+            self.journal.push((key, account));
+        }
+    }
+
+    /// Move `value` between accounts of the world state, false if `from` cannot afford it
+    pub(crate) fn transfer(&mut self, from: Address, to: Address, value: Word) -> bool {
+        let Some(world) = self.config.test_state.clone() else {
+            return true;
+        };
+        if !value.is_zero() {
+            self.journal_account(from);
+            self.journal_account(to);
+        }
+        let transferred = world.borrow_mut().transfer(&from, &to, value);
+        transferred
+    }
+
+    /// Write the storage of every account `storage` holds to the world state
+    fn write_storage(&mut self, storage: &std::collections::HashMap<Address, std::collections::HashMap<Word, Word>>) {
+        if let Some(world) = self.config.test_state.clone() {
+            for (address, slots) in storage {
+                self.journal_account(*address);
+                world.borrow_mut().set_storage(address, slots);
+            }
+        }
+    }
+
+    /// Write the storage of every account this frame holds to the world state
+    fn flush_storage(&mut self) {
+        let storage = std::mem::take(&mut self.storage);
+        self.write_storage(&storage);
+        self.storage = storage;
+    }
+
+    /// Pick up storage changes that nested calls made to the accounts this frame holds
+    fn reload_storage(&mut self) {
+        if let Some(ref world) = self.config.test_state {
+            let world = world.borrow();
+            for (address, slots) in self.storage.iter_mut() {
+                *slots = world.storage(address);
+            }
         }
+    }
 
+    /// Record a log emitted by the current contract, numbered after the ones before it
+    ///
+    /// Fails in a static context, as every LOGn modifies state.
+    fn push_log(&mut self, topics: Vec<Word>, data: Vec<u8>) -> Result<(), EvmError> {
+        if self.static_context {
+            return Err(EvmError::Unknown("LOG not allowed in static context".to_string()));
+        }
+        self.logs.push(crate::types::Log {
+            address: self.address,
+            topics,
+            data,
+            block_number: self.block_number,
+            log_index: self.logs.len() as u64,
+            transaction_index: self.config.transaction_index,
+        });
         Ok(())
     }
 
-    /// Execute a specific opcode
-    fn execute_opcode(&mut self, opcode: crate::opcodes::Opcode) -> Result<(), EvmError> {
+    /// Undo the storage writes, logs and world state changes of this frame, as a
+    /// REVERT or an error does
+    fn roll_back(&mut self) {
+        self.storage = std::collections::HashMap::from([(self.address, self.original_storage.clone())]);
+        self.logs.clear();
+        if let Some(ref world) = self.config.test_state {
+            let mut world = world.borrow_mut();
+            for (key, account) in self.journal.drain(..).rev() {
+                match account {
+                    Some(account) => world.accounts.insert(key, account),
+                    None => world.accounts.remove(&key),
+                };
+            }
+        }
+    }
+
+    /// Take over the journal of a nested frame that succeeded, so that reverting
+    /// this frame also undoes what the nested one changed
+    fn keep_frame(&mut self, frame: &mut EvmState) {
+        self.journal.append(&mut frame.journal);
+    }
+
+    /// Stop execution after an error (invalid opcode, out of gas, ...)
+    ///
+    /// Unlike REVERT, an aborted context returns no data to its caller.
+    /// An invalid or undefined opcode also consumes all remaining gas.
+    pub fn abort(&mut self, error: EvmError) {
+        if matches!(error, EvmError::InvalidOpcode(_) | EvmError::Invalid) {
+            self.gas_tracker.consume_all();
+        }
+        self.roll_back();
+        self.reverted = true;
+        self.output.clear();
+        self.outcome = Outcome::Halted(error);
+    }
+
+    /// Execute a specific opcode and tell where execution continues
+    fn execute_opcode(&mut self, opcode: crate::opcodes::Opcode) -> Result<PcAction, EvmError> {
+        match opcode {
+            crate::opcodes::Opcode::Jump => {
+                let destination = self.stack.pop()?;
+                Ok(PcAction::Jump(self.jump_target(destination)?))
+            }
+            
+            crate::opcodes::Opcode::Jumpi => {
+                let args = self.stack.pop_n(2)?;
+                let (destination, condition) = (args[0], args[1]);
+                
+                // Only jump if condition is non-zero
+                if condition.is_zero() {
+                    Ok(PcAction::Advance)
+                } else {
+                    Ok(PcAction::Jump(self.jump_target(destination)?))
+                }
+            }
+            
+            _ => self.execute_straight_line(opcode).map(|()| PcAction::Advance),
+        }
+    }
+
+    /// Execute an opcode that does not transfer control
+    fn execute_straight_line(&mut self, opcode: crate::opcodes::Opcode) -> Result<(), EvmError> {
         match opcode {
             crate::opcodes::Opcode::Stop => {
                 self.halted = true;
@@ -149,16 +599,15 @@ impl EvmState {
             crate::opcodes::Opcode::Push25 | crate::opcodes::Opcode::Push26 | crate::opcodes::Opcode::Push27 | 
             crate::opcodes::Opcode::Push28 | crate::opcodes::Opcode::Push29 | crate::opcodes::Opcode::Push30 | 
             crate::opcodes::Opcode::Push31 | crate::opcodes::Opcode::Push32 => {
-                let size = (opcode as u8 - 0x60) + 1;
+                let size = (opcode.to_byte() - 0x60) + 1;
                 let size = size as usize;
                 
-                if self.program_counter + size >= self.code.len() {
-                    return Err(EvmError::Unknown("Invalid PUSH operation".to_string()));
-                }
-                
+                // Operand bytes past the end of the code read as zero, down to a
+                // bare PUSH as the last byte, which pushes zero
                 let mut value = Word::zero();
                 for i in 0..size {
-                    value = value << 8 | Word::from(self.code[self.program_counter + 1 + i]);
+                    let byte = self.code.get(self.program_counter + 1 + i).copied().unwrap_or(0);
+                    value = value << 8 | Word::from(byte);
                 }
                 
                 self.stack.push(value)?;
@@ -167,32 +616,32 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Add => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
                 let result = a.overflowing_add(b).0; // This ensures wrapping behavior
                 self.stack.push(result)?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Mul => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
                 let result = a.overflowing_mul(b).0; // This ensures wrapping behavior
                 self.stack.push(result)?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Sub => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
                 let result = a.overflowing_sub(b).0; // This ensures wrapping behavior
                 self.stack.push(result)?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Div => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
                 if b.is_zero() {
                     self.stack.push(Word::zero())?;
                 } else {
@@ -202,8 +651,8 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Mod => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
                 if b.is_zero() {
                     self.stack.push(Word::zero())?;
                 } else {
@@ -213,9 +662,8 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Addmod => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
-                let m = self.stack.pop()?;
+                let args = self.stack.pop_n(3)?;
+                let (a, b, m) = (args[0], args[1], args[2]);
                 if m.is_zero() {
                     self.stack.push(Word::zero())?;
                 } else {
@@ -227,9 +675,8 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Mulmod => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
-                let m = self.stack.pop()?;
+                let args = self.stack.pop_n(3)?;
+                let (a, b, m) = (args[0], args[1], args[2]);
                 if m.is_zero() {
                     self.stack.push(Word::zero())?;
                 } else {
@@ -249,24 +696,18 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Sdiv => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
                 if b.is_zero() {
                     self.stack.push(Word::zero())?;
                 } else {
-                    // Handle signed division
-                    let sign_a = (a >> 255) & Word::from(1);
-                    let sign_b = (b >> 255) & Word::from(1);
-                    
-                    // Convert to absolute values
-                    let abs_a = if sign_a.is_zero() { a } else { !a + Word::from(1) };
-                    let abs_b = if sign_b.is_zero() { b } else { !b + Word::from(1) };
-                    
-                    // Perform unsigned division
-                    let abs_result = abs_a / abs_b;
+                    // Divide the magnitudes
+                    let abs_result = crate::util::abs(a) / crate::util::abs(b);
                     
-                    // Apply sign: result is negative if exactly one operand is negative
-                    let result = if sign_a != sign_b { !abs_result + Word::from(1) } else { abs_result };
+                    // Apply sign: result is negative if exactly one operand is negative.
+                    // Negation wraps, so a zero quotient stays zero and -2^255 / -1 = -2^255
+                    let negative = crate::util::is_negative(a) != crate::util::is_negative(b);
+                    let result = if negative { crate::util::negate(abs_result) } else { abs_result };
                     
                     self.stack.push(result)?;
                 }
@@ -274,24 +715,16 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Smod => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
                 if b.is_zero() {
                     self.stack.push(Word::zero())?;
                 } else {
-                    // Handle signed modulo
-                    let sign_a = (a >> 255) & Word::from(1);
-                    let sign_b = (b >> 255) & Word::from(1);
-                    
-                    // Convert to absolute values
-                    let abs_a = if sign_a.is_zero() { a } else { !a + Word::from(1) };
-                    let abs_b = if sign_b.is_zero() { b } else { !b + Word::from(1) };
+                    // Take the remainder of the magnitudes
+                    let abs_result = crate::util::abs(a) % crate::util::abs(b);
                     
-                    // Perform unsigned modulo
-                    let abs_result = abs_a % abs_b;
-                    
-                    // Apply sign: result has the same sign as the dividend (a)
-                    let result = if sign_a.is_zero() { abs_result } else { !abs_result + Word::from(1) };
+                    // Apply sign: result has the same sign as the dividend (a); negating zero wraps to zero
+                    let result = if crate::util::is_negative(a) { crate::util::negate(abs_result) } else { abs_result };
                     
                     self.stack.push(result)?;
                 }
@@ -299,10 +732,13 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Signextend => {
-                let b = self.stack.pop()?;
-                let x = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (b, x) = (args[0], args[1]);
                 
+                // Byte b counts from the least significant end; from b = 31 on the
+                // sign bit is already the top bit, so x is returned unchanged
                 if b < Word::from(31) {
+                    // At most 30 * 8 + 7 = 247, so the shifts below stay in range
                     let bit_pos = b.as_u32() * 8 + 7;
                     let bit = (x >> bit_pos) & Word::from(1);
                     if bit.is_zero() {
@@ -321,34 +757,24 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Slt => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
-                
-                // Handle signed comparison
-                let sign_a = (a >> 255) & Word::from(1);
-                let sign_b = (b >> 255) & Word::from(1);
-                
-                // If signs are different, negative number is less than positive
-                if sign_a != sign_b {
-                    self.stack.push(if sign_a.is_zero() { Word::zero() } else { Word::from(1) })?;
-                } else {
-                    // Same sign, compare as unsigned
-                    self.stack.push(if a < b { Word::from(1) } else { Word::zero() })?;
-                }
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
+                let less = crate::util::signed_cmp(a, b) == std::cmp::Ordering::Less;
+                self.stack.push(if less { Word::from(1) } else { Word::zero() })?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Sgt => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
-                // Signed greater than - for now treat as regular greater than
-                self.stack.push(if a > b { Word::from(1) } else { Word::zero() })?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
+                let greater = crate::util::signed_cmp(a, b) == std::cmp::Ordering::Greater;
+                self.stack.push(if greater { Word::from(1) } else { Word::zero() })?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Byte => {
-                let i = self.stack.pop()?;
-                let x = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (i, x) = (args[0], args[1]);
                 
                 if i >= Word::from(32) {
                     self.stack.push(Word::zero())?;
@@ -365,8 +791,8 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Sha3 => {
-                let offset = self.stack.pop()?;
-                let size = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (offset, size) = (args[0], args[1]);
                 
                 let offset_usize = offset.as_usize();
                 let size_usize = size.as_usize();
@@ -391,6 +817,7 @@ impl EvmState {
             crate::opcodes::Opcode::Balance => {
                 // Pop the address from the stack
                 let address = self.stack.pop()?;
+                self.charge_account_access(word_to_address(address))?;
                 
                 // Check if we have test state configuration
                 if let Some(ref test_state) = self.config.test_state {
@@ -402,8 +829,7 @@ impl EvmState {
                     if let Some(account_state) = test_state_borrowed.accounts.get(&address_str) {
                         if let Some(ref balance_hex) = account_state.balance {
                             // Parse the balance from hex string
-                            let balance_clean = balance_hex.trim_start_matches("0x");
-                            let balance = U256::from_str_radix(balance_clean, 16).unwrap_or_default();
+                            let balance = crate::types::word_from_hex(balance_hex).unwrap_or_default();
                             self.stack.push(balance)?;
                         } else {
                             // No balance specified, return 0
@@ -422,45 +848,33 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Exp => {
-                let base = self.stack.pop()?;
-                let exponent = self.stack.pop()?;
-                
-                // Handle overflow by using modular arithmetic
-                // For large exponents, we need to be careful about overflow
-                let mut result = Word::from(1);
-                let mut exp = exponent;
-                let mut current_base = base;
-                
-                while !exp.is_zero() {
-                    if exp & Word::from(1) != Word::zero() {
-                        result = result * current_base;
-                    }
-                    current_base = current_base * current_base;
-                    exp = exp >> 1;
-                }
+                let args = self.stack.pop_n(2)?;
+                let (base, exponent) = (args[0], args[1]);
+                self.gas_tracker.consume(crate::gas::exp_cost(exponent))?;
                 
-                self.stack.push(result)?;
+                // The result wraps modulo 2^256
+                self.stack.push(base.overflowing_pow(exponent).0)?;
                 Ok(())
             }
             
             // Comparison operations
             crate::opcodes::Opcode::Lt => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
                 self.stack.push(if a < b { Word::from(1) } else { Word::zero() })?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Gt => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
                 self.stack.push(if a > b { Word::from(1) } else { Word::zero() })?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Eq => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
                 self.stack.push(if a == b { Word::from(1) } else { Word::zero() })?;
                 Ok(())
             }
@@ -473,29 +887,29 @@ impl EvmState {
             
             // Bitwise operations
             crate::opcodes::Opcode::And => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
                 self.stack.push(a & b)?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Or => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
                 self.stack.push(a | b)?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Xor => {
-                let a = self.stack.pop()?;
-                let b = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (a, b) = (args[0], args[1]);
                 self.stack.push(a ^ b)?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Shl => {
-                let shift = self.stack.pop()?;
-                let value = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (shift, value) = (args[0], args[1]);
                 
                 // Handle shift left with overflow
                 let shift_amount = shift.as_u32();
@@ -509,8 +923,8 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Shr => {
-                let shift = self.stack.pop()?;
-                let value = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (shift, value) = (args[0], args[1]);
                 
                 // Handle shift right with overflow
                 let shift_amount = shift.as_u32();
@@ -524,32 +938,23 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Sar => {
-                let shift = self.stack.pop()?;
-                let value = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (shift, value) = (args[0], args[1]);
                 
                 // Handle arithmetic shift right with overflow
-                let shift_amount = shift.as_u32();
-                if shift_amount >= 256 {
+                let negative = crate::util::is_negative(value);
+                let result = if shift >= Word::from(256) {
                     // If shifting by 256 or more, result depends on sign
-                    let sign_bit = (value >> 255) & Word::from(1);
-                    if sign_bit.is_zero() {
-                        self.stack.push(Word::zero())?;
-                    } else {
-                        self.stack.push(Word::max_value())?;
-                    }
+                    if negative { Word::max_value() } else { Word::zero() }
+                } else if negative {
+                    // Shift the complement so the vacated upper bits fill with 1s;
+                    // this holds for every shift from 0 to 255
+                    !(!value >> shift.as_usize())
                 } else {
-                    // For smaller shifts, preserve sign bit
-                    let sign_bit = (value >> 255) & Word::from(1);
-                    let mut result = value >> shift_amount;
-                    
-                    // If the original number was negative, fill upper bits with 1s
-                    if !sign_bit.is_zero() {
-                        let mask = !((Word::from(1) << (256 - shift_amount)) - Word::from(1));
-                        result = result | mask;
-                    }
-                    
-                    self.stack.push(result)?;
-                }
+                    value >> shift.as_usize()
+                };
+                
+                self.stack.push(result)?;
                 Ok(())
             }
             
@@ -623,12 +1028,10 @@ impl EvmState {
                     return Err(EvmError::StackUnderflow);
                 }
                 
-                // The tests expect SWAPn to swap the bottom element (index 0) with the nth element from bottom (index n)
-                // So for SWAP3: swap stack[0] with stack[3]
+                // SWAPn exchanges the top item with the nth item below it
                 let stack_data = self.stack.data_mut();
-                let temp = stack_data[0];
-                stack_data[0] = stack_data[swap_index];
-                stack_data[swap_index] = temp;
+                let top = stack_data.len() - 1;
+                stack_data.swap(top, top - swap_index);
                 
                 Ok(())
             }
@@ -641,22 +1044,12 @@ impl EvmState {
             
             // Environmental information
             crate::opcodes::Opcode::Address => {
-                // Convert 20-byte address to 32-byte word by padding with zeros
-                let mut padded_address = vec![0u8; 32];
-                for (i, &byte) in self.address.iter().enumerate() {
-                    padded_address[32 - 20 + i] = byte; // Place address bytes at the end
-                }
-                self.stack.push(Word::from_big_endian(&padded_address))?;
+                self.stack.push(address_to_word(self.address))?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Caller => {
-                // Convert 20-byte caller address to 32-byte word by padding with zeros
-                let mut padded_address = vec![0u8; 32];
-                for (i, &byte) in self.caller.iter().enumerate() {
-                    padded_address[32 - 20 + i] = byte; // Place address bytes at the end
-                }
-                self.stack.push(Word::from_big_endian(&padded_address))?;
+                self.stack.push(address_to_word(self.caller))?;
                 Ok(())
             }
             
@@ -671,9 +1064,9 @@ impl EvmState {
                 
                 // Read 32 bytes starting from the offset
                 let mut data = vec![0u8; 32];
-                for i in 0..32 {
-                    if offset_usize + i < self.calldata.len() {
-                        data[i] = self.calldata[offset_usize + i];
+                for (i, byte) in data.iter_mut().enumerate() {
+                    if let Some(&value) = self.calldata.get(offset_usize + i) {
+                        *byte = value;
                     }
                     // If offset + i is out of bounds, data[i] remains 0 (already initialized)
                 }
@@ -697,46 +1090,24 @@ impl EvmState {
             
             crate::opcodes::Opcode::Returndatacopy => {
                 // Pop destOffset, offset, size from stack
-                let dest_offset = self.stack.pop()?;
-                let offset = self.stack.pop()?;
-                let size = self.stack.pop()?;
-                
-                let dest_offset_usize = dest_offset.as_usize();
-                let offset_usize = offset.as_usize();
-                let size_usize = size.as_usize();
+                let args = self.stack.pop_n(3)?;
+                let (dest_offset, offset, size) = (args[0], args[1], args[2]);
                 
                 // Copy return data to memory
-                let mut data = vec![0u8; size_usize];
-                for i in 0..size_usize {
-                    if offset_usize + i < self.return_data.len() {
-                        data[i] = self.return_data[offset_usize + i];
-                    }
-                    // If offset + i is out of bounds, data[i] remains 0 (already initialized)
-                }
-                
+                let (dest_offset_usize, size_usize) = self.charge_copy(dest_offset, size)?;
+                let data = Self::copy_padded(&self.return_data, offset, size_usize);
                 self.memory.write(dest_offset_usize, &data)?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Calldatacopy => {
                 // Pop destOffset, offset, size from stack
-                let dest_offset = self.stack.pop()?;
-                let offset = self.stack.pop()?;
-                let size = self.stack.pop()?;
-                
-                let dest_offset_usize = dest_offset.as_usize();
-                let offset_usize = offset.as_usize();
-                let size_usize = size.as_usize();
+                let args = self.stack.pop_n(3)?;
+                let (dest_offset, offset, size) = (args[0], args[1], args[2]);
                 
                 // Copy calldata to memory
-                let mut data = vec![0u8; size_usize];
-                for i in 0..size_usize {
-                    if offset_usize + i < self.calldata.len() {
-                        data[i] = self.calldata[offset_usize + i];
-                    }
-                    // If offset + i is out of bounds, data[i] remains 0 (already initialized)
-                }
-                
+                let (dest_offset_usize, size_usize) = self.charge_copy(dest_offset, size)?;
+                let data = Self::copy_padded(&self.calldata, offset, size_usize);
                 self.memory.write(dest_offset_usize, &data)?;
                 Ok(())
             }
@@ -749,23 +1120,12 @@ impl EvmState {
             
             crate::opcodes::Opcode::Codecopy => {
                 // Pop destOffset, offset, size from stack
-                let dest_offset = self.stack.pop()?;
-                let offset = self.stack.pop()?;
-                let size = self.stack.pop()?;
-                
-                let dest_offset_usize = dest_offset.as_usize();
-                let offset_usize = offset.as_usize();
-                let size_usize = size.as_usize();
+                let args = self.stack.pop_n(3)?;
+                let (dest_offset, offset, size) = (args[0], args[1], args[2]);
                 
                 // Copy code to memory
-                let mut data = vec![0u8; size_usize];
-                for i in 0..size_usize {
-                    if offset_usize + i < self.code.len() {
-                        data[i] = self.code[offset_usize + i];
-                    }
-                    // If offset + i is out of bounds, data[i] remains 0 (already initialized)
-                }
-                
+                let (dest_offset_usize, size_usize) = self.charge_copy(dest_offset, size)?;
+                let data = Self::copy_padded(&self.code, offset, size_usize);
                 self.memory.write(dest_offset_usize, &data)?;
                 Ok(())
             }
@@ -773,6 +1133,7 @@ impl EvmState {
             crate::opcodes::Opcode::Extcodesize => {
                 // Pop the address from the stack
                 let address = self.stack.pop()?;
+                self.charge_account_access(word_to_address(address))?;
                 
                 // Check if we have test state configuration
                 if let Some(ref test_state) = self.config.test_state {
@@ -784,13 +1145,7 @@ impl EvmState {
                     if let Some(account_state) = test_state_borrowed.accounts.get(&address_str) {
                         if let Some(ref code) = &account_state.code {
                             // Parse the actual code from test state
-                            let code_clean = code.bin.trim_start_matches("0x");
-                            let code_bytes = match hex::decode(code_clean) {
-                                Ok(bytes) => bytes,
-                                Err(_) => {
-                                    vec![]
-                                }
-                            };
+                            let code_bytes = crate::util::parse_hex(&code.bin).unwrap_or_default();
                             
                             // Return the actual code size
                             self.stack.push(Word::from(code_bytes.len()))?;
@@ -811,65 +1166,19 @@ impl EvmState {
             
             crate::opcodes::Opcode::Extcodecopy => {
                 // Pop size, offset, destOffset, address from stack (LIFO order)
-                let address = self.stack.pop()?;
-                let dest_offset = self.stack.pop()?;
-                let offset = self.stack.pop()?;
-                let size = self.stack.pop()?;
-                
-                
-                // Check if values can fit in usize (reasonable bounds for memory operations)
-                if dest_offset > Word::from(usize::MAX) || offset > Word::from(usize::MAX) || size > Word::from(usize::MAX) {
-                    return Err(EvmError::MemoryOutOfBounds);
-                }
-                
-                let dest_offset_usize = dest_offset.as_usize();
-                let offset_usize = offset.as_usize();
-                let size_usize = size.as_usize();
-                
-                // Check if we have test state configuration
-                if let Some(ref test_state) = self.config.test_state {
-                    // Convert address to string format for lookup
-                    let address_str = format!("0x{:040x}", address);
-                    let test_state_borrowed = test_state.borrow();
-                    
-                    // Check if this address has code in the test state
-                    if let Some(account_state) = test_state_borrowed.accounts.get(&address_str) {
-                        if let Some(ref code) = &account_state.code {
-                            // Parse the actual code from test state
-                            let code_clean = code.bin.trim_start_matches("0x");
-                            let code_bytes = match hex::decode(code_clean) {
-                                Ok(bytes) => bytes,
-                                Err(_) => {
-                                    vec![]
-                                }
-                            };
-                            
-                            
-                            // Create data buffer and copy code bytes
-                            let mut data = vec![0u8; size_usize];
-                            for i in 0..size_usize {
-                                if offset_usize + i < code_bytes.len() {
-                                    data[i] = code_bytes[offset_usize + i];
-                                }
-                                // If offset + i is out of bounds, data[i] remains 0 (already initialized)
-                            }
-                            
-                            self.memory.write(dest_offset_usize, &data)?;
-                        } else {
-                            // Account exists but has no code, write zeros
-                            let data = vec![0u8; size_usize];
-                            self.memory.write(dest_offset_usize, &data)?;
-                        }
-                    } else {
-                        // Account not found in test state, write zeros
-                        let data = vec![0u8; size_usize];
-                        self.memory.write(dest_offset_usize, &data)?;
-                    }
-                } else {
-                    // No test state, write zeros
-                    let data = vec![0u8; size_usize];
-                    self.memory.write(dest_offset_usize, &data)?;
-                }
+                let args = self.stack.pop_n(4)?;
+                let (address, dest_offset, offset, size) = (args[0], args[1], args[2], args[3]);
+                
+                // A missing account or one without code copies zeros
+                let (dest_offset_usize, size_usize) = self.charge_copy(dest_offset, size)?;
+                let code = self
+                    .config
+                    .test_state
+                    .as_ref()
+                    .map(|world| world.borrow().code(&word_to_address(address)))
+                    .unwrap_or_default();
+                let data = Self::copy_padded(&code, offset, size_usize);
+                self.memory.write(dest_offset_usize, &data)?;
                 Ok(())
             }
             
@@ -891,8 +1200,7 @@ impl EvmState {
                     let test_state_borrowed = test_state.borrow();
                     if let Some(account_state) = test_state_borrowed.accounts.get(&current_address_str) {
                         if let Some(ref balance_hex) = account_state.balance {
-                            let balance_clean = balance_hex.trim_start_matches("0x");
-                            U256::from_str_radix(balance_clean, 16).unwrap_or_default()
+                            crate::types::word_from_hex(balance_hex).unwrap_or_default()
                         } else {
                             Word::zero()
                         }
@@ -904,18 +1212,19 @@ impl EvmState {
                 };
                 
                 // Transfer balance to beneficiary
+                self.journal_account(word_to_address(beneficiary));
+                self.journal_account(self.address);
                 if let Some(ref test_state) = self.config.test_state {
                     let mut test_state_borrowed = test_state.borrow_mut();
                     // Get or create beneficiary account
                     let beneficiary_account = test_state_borrowed.accounts.entry(beneficiary_str.clone()).or_insert_with(|| crate::types::AccountState {
                         balance: Some("0x0".to_string()),
-                        code: None,
+                        ..Default::default()
                     });
                     
                     // Add current contract's balance to beneficiary
                     let beneficiary_balance = if let Some(ref balance_hex) = beneficiary_account.balance {
-                        let balance_clean = balance_hex.trim_start_matches("0x");
-                        U256::from_str_radix(balance_clean, 16).unwrap_or_default()
+                        crate::types::word_from_hex(balance_hex).unwrap_or_default()
                     } else {
                         Word::zero()
                     };
@@ -940,64 +1249,25 @@ impl EvmState {
             crate::opcodes::Opcode::Extcodehash => {
                 // Pop the address from the stack
                 let address = self.stack.pop()?;
-                
-                // Check if we have test state configuration
-                if let Some(ref test_state) = self.config.test_state {
-                    // Convert address to string format for lookup
-                    let address_str = format!("0x{:040x}", address);
-                    let test_state_borrowed = test_state.borrow();
-                    
-                    // Check if this address has code in the test state
-                    if let Some(account_state) = test_state_borrowed.accounts.get(&address_str) {
-                        if let Some(ref code) = &account_state.code {
-                            // Parse the actual code from test state
-                            let code_clean = code.bin.trim_start_matches("0x");
-                            let code_bytes = match hex::decode(code_clean) {
-                                Ok(bytes) => bytes,
-                                Err(_) => {
-                                    vec![]
-                                }
-                            };
-                            
-                            if code_bytes.is_empty() {
-                                // Empty code, return 0
-                                self.stack.push(Word::zero())?;
-                            } else {
-                                // Use real Keccak-256 (SHA3) hash function
-                                use sha3::{Digest, Keccak256};
-                                let mut hasher = Keccak256::new();
-                                hasher.update(&code_bytes);
-                                let result = hasher.finalize();
-                                
-                                // Convert the 32-byte hash result to a Word
-                                let mut hash_bytes = [0u8; 32];
-                                hash_bytes.copy_from_slice(&result);
-                                let hash = Word::from_big_endian(&hash_bytes);
-                                
-                                self.stack.push(hash)?;
-                            }
-                        } else {
-                            // Account exists but has no code
-                            self.stack.push(Word::zero())?;
-                        }
-                    } else {
-                        // Account doesn't exist in test state
-                        self.stack.push(Word::zero())?;
+                self.charge_account_access(word_to_address(address))?;
+                
+                // A nonexistent account hashes to 0, an existing one to the hash of
+                // its code, which is keccak256("") if it has none
+                let address = word_to_address(address);
+                let hash = match self.config.test_state {
+                    Some(ref world) if world.borrow().exists(&address) => {
+                        use sha3::{Digest, Keccak256};
+                        let code = world.borrow().code(&address);
+                        Word::from_big_endian(&Keccak256::digest(&code))
                     }
-                } else {
-                    // No test state means no accounts have code, return 0
-                    self.stack.push(Word::zero())?;
-                }
+                    _ => Word::zero(),
+                };
+                self.stack.push(hash)?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Origin => {
-                // Convert 20-byte origin address to 32-byte word by padding with zeros
-                let mut padded_address = vec![0u8; 32];
-                for (i, &byte) in self.origin.iter().enumerate() {
-                    padded_address[32 - 20 + i] = byte; // Place address bytes at the end
-                }
-                self.stack.push(Word::from_big_endian(&padded_address))?;
+                self.stack.push(address_to_word(self.origin))?;
                 Ok(())
             }
             
@@ -1010,20 +1280,21 @@ impl EvmState {
             //TODO
             // Block information
             crate::opcodes::Opcode::Blockhash => {
-                // Pop the block number from the stack
-                let _block_number = self.stack.pop()?;
-                // For now, return 0 (in a real EVM this would return actual block hash)
-                self.stack.push(Word::zero())?;
+                // Only the 256 most recent blocks are available; the current
+                // block and future blocks hash to zero
+                let requested = self.stack.pop()?;
+                let current = Word::from(self.block_number);
+                let hash = if requested < current && current - requested <= Word::from(BLOCKHASH_WINDOW) {
+                    self.config.block_hashes.get(&requested.as_u64()).copied().unwrap_or_default()
+                } else {
+                    Word::zero()
+                };
+                self.stack.push(hash)?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Coinbase => {
-                // Convert 20-byte coinbase address to 32-byte word by padding with zeros
-                let mut padded_address = vec![0u8; 32];
-                for (i, &byte) in self.coinbase.iter().enumerate() {
-                    padded_address[32 - 20 + i] = byte; // Place address bytes at the end
-                }
-                self.stack.push(Word::from_big_endian(&padded_address))?;
+                self.stack.push(address_to_word(self.coinbase))?;
                 Ok(())
             }
             
@@ -1038,7 +1309,12 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Difficulty => {
-                self.stack.push(self.block_difficulty)?;
+                // Since the Merge the same opcode is PREVRANDAO
+                if self.config.is_eip_enabled(4399, crate::types::Hardfork::Paris) {
+                    self.stack.push(self.config.block_prevrandao)?;
+                } else {
+                    self.stack.push(self.block_difficulty)?;
+                }
                 Ok(())
             }
             
@@ -1057,6 +1333,23 @@ impl EvmState {
                 Ok(())
             }
             
+            crate::opcodes::Opcode::Blobhash => {
+                // Versioned hash of the transaction's blob at the given index, zero if out of range
+                let index = self.stack.pop()?;
+                let hash = if index < Word::from(self.config.blob_versioned_hashes.len()) {
+                    self.config.blob_versioned_hashes[index.as_usize()]
+                } else {
+                    Word::zero()
+                };
+                self.stack.push(hash)?;
+                Ok(())
+            }
+            
+            crate::opcodes::Opcode::Blobbasefee => {
+                self.stack.push(self.config.block_blob_base_fee)?;
+                Ok(())
+            }
+            
             crate::opcodes::Opcode::Selfbalance => {
                 // SELFBALANCE returns the balance of the current executing contract
                 // The current contract address is stored in self.address
@@ -1075,8 +1368,7 @@ impl EvmState {
                     if let Some(account_state) = test_state_borrowed.accounts.get(&address_str) {
                         if let Some(ref balance_hex) = account_state.balance {
                             // Parse the balance from hex string
-                            let balance_clean = balance_hex.trim_start_matches("0x");
-                            let balance = U256::from_str_radix(balance_clean, 16).unwrap_or_default();
+                            let balance = crate::types::word_from_hex(balance_hex).unwrap_or_default();
                             self.stack.push(balance)?;
                         } else {
                             // No balance specified, return 0
@@ -1096,32 +1388,21 @@ impl EvmState {
             // Memory operations
             crate::opcodes::Opcode::Mload => {
                 let offset = self.stack.pop()?;
-                let offset_usize = offset.as_usize();
-                let data = self.memory.read(offset_usize, 32)?; // Read 32 bytes (1 word)
-                let mut padded_data = vec![0u8; 32];
-                for (i, &byte) in data.iter().enumerate() {
-                    if i < 32 {
-                        padded_data[i] = byte;
-                    }
-                }
-                let value = Word::from_big_endian(&padded_data);
+                let value = self.memory.load_word(offset.as_usize())?;
                 self.stack.push(value)?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Mstore => {
-                let offset = self.stack.pop()?;
-                let value = self.stack.pop()?;
-                let offset_usize = offset.as_usize();
-                let mut data = vec![0u8; 32];
-                value.to_big_endian(&mut data);
-                self.memory.write(offset_usize, &data)?;
+                let args = self.stack.pop_n(2)?;
+                let (offset, value) = (args[0], args[1]);
+                self.memory.store_word(offset.as_usize(), value)?;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Mstore8 => {
-                let offset = self.stack.pop()?;
-                let value = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (offset, value) = (args[0], args[1]);
                 let offset_usize = offset.as_usize();
                 
                 // MSTORE8 stores only the least significant byte
@@ -1132,17 +1413,8 @@ impl EvmState {
             }
             
             crate::opcodes::Opcode::Msize => {
-                // MSIZE returns the highest accessed memory index, rounded up to the nearest word boundary
-                // If no memory has been accessed, return 0
-                if !self.memory.has_been_accessed() {
-                    self.stack.push(Word::zero())?;
-                } else {
-                    let highest_accessed = self.memory.highest_accessed_index();
-                    let size_in_words = (highest_accessed + 32) / 32; // Round up to nearest word
-                    let size_in_bytes = size_in_words * 32;
-                    self.stack.push(Word::from(size_in_bytes))?;
-                }
-                Ok(())
+                // MSIZE returns the active memory size, a multiple of the word size
+                self.stack.push(Word::from(self.memory.size()))
             }
             
             // Gas operations
@@ -1158,64 +1430,10 @@ impl EvmState {
                 Ok(())
             }
             
-            // Jump operations
-            crate::opcodes::Opcode::Jump => {
-                let destination = self.stack.pop()?;
-                let dest_usize = destination.as_usize();
-                
-                // Check if destination is valid (within code bounds)
-                if dest_usize >= self.code.len() {
-                    return Err(EvmError::InvalidJumpDestination);
-                }
-                
-                // Check if destination points to a JUMPDEST opcode
-                if self.code[dest_usize] != 0x5b { // JUMPDEST opcode
-                    return Err(EvmError::InvalidJumpDestination);
-                }
-                
-                // Check if destination is at a valid instruction boundary
-                if !self.is_valid_jump_destination(dest_usize) {
-                    return Err(EvmError::InvalidJumpDestination);
-                }
-                
-                self.program_counter = dest_usize;
-                Ok(())
-            }
-            
-            crate::opcodes::Opcode::Jumpi => {
-                let destination = self.stack.pop()?;
-                let condition = self.stack.pop()?;
-                
-                // Track whether JUMPI actually jumped
-                self.last_jumpi_jumped = false;
-                
-                // Only jump if condition is non-zero
-                if !condition.is_zero() {
-                    let dest_usize = destination.as_usize();
-                    
-                    // Check if destination is valid (within code bounds)
-                    if dest_usize >= self.code.len() {
-                        return Err(EvmError::InvalidJumpDestination);
-                    }
-                    
-                    // Check if destination points to a JUMPDEST opcode
-                    if self.code[dest_usize] != 0x5b { // JUMPDEST opcode
-                        return Err(EvmError::InvalidJumpDestination);
-                    }
-                    
-                    // Check if destination is at a valid instruction boundary
-                    if !self.is_valid_jump_destination(dest_usize) {
-                        return Err(EvmError::InvalidJumpDestination);
-                    }
-                    
-                    self.program_counter = dest_usize;
-                    self.last_jumpi_jumped = true;
-                }
-                Ok(())
-            }
-            
-            crate::opcodes::Opcode::Jumpdest => {
-                // JUMPDEST is a no-op, just continue execution
+            crate::opcodes::Opcode::Invalid => Err(EvmError::Invalid),
+            
+            crate::opcodes::Opcode::Jumpdest => {
+                // JUMPDEST is a no-op, just continue execution
                 Ok(())
             }
             
@@ -1226,17 +1444,18 @@ impl EvmState {
                     return Err(EvmError::Unknown("SSTORE not allowed in static context".to_string()));
                 }
                 
-                let key = self.stack.pop()?;
-                let value = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (key, value) = (args[0], args[1]);
                 
                 // Calculate gas cost based on storage operation type
-                let current_value = self.storage.get(&key).copied().unwrap_or(Word::zero());
+                let current_value = self.storage_value(key);
                 let gas_cost = if current_value.is_zero() && !value.is_zero() {
                     // Setting a new non-zero value
                     crate::gas::GAS_SSTORE_SET
                 } else if !current_value.is_zero() && value.is_zero() {
-                    // Clearing a non-zero value
-                    crate::gas::GAS_SSTORE_CLEAR
+                    // Clearing a non-zero value costs a reset and earns a refund
+                    self.gas_tracker.add_refund(crate::gas::GAS_SSTORE_CLEAR);
+                    crate::gas::GAS_SSTORE_RESET
                 } else {
                     // Resetting an existing value
                     crate::gas::GAS_SSTORE_RESET
@@ -1246,17 +1465,16 @@ impl EvmState {
                 self.gas_tracker.consume(gas_cost)?;
                 
                 // Store the value at the given key
-                self.storage.insert(key, value);
+                self.storage.entry(self.address).or_default().insert(key, value);
                 Ok(())
             }
             
             crate::opcodes::Opcode::Sload => {
                 let key = self.stack.pop()?;
-                
-                // SLOAD gas is already consumed in step(), so no need to consume here
+                self.charge_storage_access(key)?;
                 
                 // Load the value from storage, return 0 if not found
-                let value = self.storage.get(&key).copied().unwrap_or(Word::zero());
+                let value = self.storage_value(key);
                 self.stack.push(value)?;
                 Ok(())
             }
@@ -1266,304 +1484,164 @@ impl EvmState {
                 // LOG0 gas is already consumed in step(), so no need to consume here
                 
                 // LOG0 consumes 2 values from stack: offset and size
-                let offset = self.stack.pop()?;
-                let size = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (offset, size) = (args[0], args[1]);
                 
                 // Read data from memory at the specified offset and size
                 let offset_usize = offset.as_usize();
                 let size_usize = size.as_usize();
                 let data = self.memory.read(offset_usize, size_usize)?;
                 
-                // Create log entry
-                let log = crate::types::Log {
-                    address: self.address,
-                    topics: vec![], // LOG0 has no topics
-                    data,
-                };
-                
-                // Add to logs
-                self.logs.push(log);
-                Ok(())
+                self.push_log(vec![], data)
             }
             
             crate::opcodes::Opcode::Log1 => {
-                // Check if we're in static context (STATICCALL)
-                if self.static_context {
-                    return Err(EvmError::Unknown("LOG1 not allowed in static context".to_string()));
-                }
-                
                 // LOG1 gas is already consumed in step(), so no need to consume here
                 
                 // LOG1 consumes 3 values from stack: offset, size, and topic1
-                let offset = self.stack.pop()?;
-                let size = self.stack.pop()?;
-                let topic1 = self.stack.pop()?;
+                let args = self.stack.pop_n(3)?;
+                let (offset, size, topic1) = (args[0], args[1], args[2]);
                 
                 // Read data from memory at the specified offset and size
                 let offset_usize = offset.as_usize();
                 let size_usize = size.as_usize();
                 let data = self.memory.read(offset_usize, size_usize)?;
                 
-                // Create log entry
-                let log = crate::types::Log {
-                    address: self.address,
-                    topics: vec![topic1], // LOG1 has 1 topic
-                    data,
-                };
-                
-                // Add to logs
-                self.logs.push(log);
-                Ok(())
+                self.push_log(vec![topic1], data)
             }
             
             crate::opcodes::Opcode::Log2 => {
                 // LOG2 gas is already consumed in step(), so no need to consume here
                 
                 // LOG2 consumes 4 values from stack: offset, size, topic1, and topic2
-                let offset = self.stack.pop()?;
-                let size = self.stack.pop()?;
-                let topic1 = self.stack.pop()?;
-                let topic2 = self.stack.pop()?;
+                let args = self.stack.pop_n(4)?;
+                let (offset, size, topic1, topic2) = (args[0], args[1], args[2], args[3]);
                 
                 // Read data from memory at the specified offset and size
                 let offset_usize = offset.as_usize();
                 let size_usize = size.as_usize();
                 let data = self.memory.read(offset_usize, size_usize)?;
                 
-                // Create log entry
-                let log = crate::types::Log {
-                    address: self.address,
-                    topics: vec![topic1, topic2], // LOG2 has 2 topics
-                    data,
-                };
-                
-                // Add to logs
-                self.logs.push(log);
-                Ok(())
+                self.push_log(vec![topic1, topic2], data)
             }
             
             crate::opcodes::Opcode::Log3 => {
                 // LOG3 gas is already consumed in step(), so no need to consume here
                 
                 // LOG3 consumes 5 values from stack: offset, size, topic1, topic2, and topic3
-                let offset = self.stack.pop()?;
-                let size = self.stack.pop()?;
-                let topic1 = self.stack.pop()?;
-                let topic2 = self.stack.pop()?;
-                let topic3 = self.stack.pop()?;
+                let args = self.stack.pop_n(5)?;
+                let (offset, size, topic1, topic2, topic3) = (args[0], args[1], args[2], args[3], args[4]);
                 
                 // Read data from memory at the specified offset and size
                 let offset_usize = offset.as_usize();
                 let size_usize = size.as_usize();
                 let data = self.memory.read(offset_usize, size_usize)?;
                 
-                // Create log entry
-                let log = crate::types::Log {
-                    address: self.address,
-                    topics: vec![topic1, topic2, topic3], // LOG3 has 3 topics
-                    data,
-                };
-                
-                // Add to logs
-                self.logs.push(log);
-                Ok(())
+                self.push_log(vec![topic1, topic2, topic3], data)
             }
             
             crate::opcodes::Opcode::Log4 => {
                 // LOG4 gas is already consumed in step(), so no need to consume here
                 
                 // LOG4 consumes 6 values from stack: offset, size, topic1, topic2, topic3, and topic4
-                let offset = self.stack.pop()?;
-                let size = self.stack.pop()?;
-                let topic1 = self.stack.pop()?;
-                let topic2 = self.stack.pop()?;
-                let topic3 = self.stack.pop()?;
-                let topic4 = self.stack.pop()?;
+                let args = self.stack.pop_n(6)?;
+                let (offset, size, topic1, topic2, topic3, topic4) = (args[0], args[1], args[2], args[3], args[4], args[5]);
                 
                 // Read data from memory at the specified offset and size
                 let offset_usize = offset.as_usize();
                 let size_usize = size.as_usize();
                 let data = self.memory.read(offset_usize, size_usize)?;
                 
-                // Create log entry
-                let log = crate::types::Log {
-                    address: self.address,
-                    topics: vec![topic1, topic2, topic3, topic4], // LOG4 has 4 topics
-                    data,
-                };
-                
-                // Add to logs
-                self.logs.push(log);
-                Ok(())
+                self.push_log(vec![topic1, topic2, topic3, topic4], data)
             }
             
             // System operations
             crate::opcodes::Opcode::Return => {
-                // RETURN gas is already consumed in step(), so no need to consume here
-                
                 // RETURN consumes 2 values from stack: offset and size
-                let offset = self.stack.pop()?;
-                let size = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (offset, size) = (args[0], args[1]);
                 
-                // Read data from memory at the specified offset and size
-                let offset_usize = offset.as_usize();
-                let size_usize = size.as_usize();
-                let data = self.memory.read(offset_usize, size_usize)?;
+                // Read data from memory at the specified offset and size,
+                // charging for any memory expansion first
+                let (offset_usize, size_usize) = self.charge_memory_expansion(offset, size)?;
+                let data = if size_usize == 0 {
+                    Vec::new()
+                } else {
+                    self.memory.read(offset_usize, size_usize)?
+                };
                 
-                // Set return data
-                self.return_data = data;
+                // Set the output of this context
+                self.output = data;
                 
                 // Halt execution
                 self.halted = true;
+                self.outcome = Outcome::Returned;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Revert => {
-                // REVERT gas is already consumed in step(), so no need to consume here
-                
                 // REVERT consumes 2 values from stack: offset and size
-                let offset = self.stack.pop()?;
-                let size = self.stack.pop()?;
+                let args = self.stack.pop_n(2)?;
+                let (offset, size) = (args[0], args[1]);
                 
-                // Read data from memory at the specified offset and size
-                let offset_usize = offset.as_usize();
-                let size_usize = size.as_usize();
-                let data = self.memory.read(offset_usize, size_usize)?;
+                // Read data from memory at the specified offset and size,
+                // charging for any memory expansion first
+                let (offset_usize, size_usize) = self.charge_memory_expansion(offset, size)?;
+                let data = if size_usize == 0 {
+                    Vec::new()
+                } else {
+                    self.memory.read(offset_usize, size_usize)?
+                };
                 
-                // Set return data
-                self.return_data = data;
+                // Set the output of this context
+                self.output = data;
                 
-                // Set reverted state
+                // Set reverted state, undoing what this frame changed
+                self.roll_back();
                 self.reverted = true;
+                self.outcome = Outcome::Reverted;
                 Ok(())
             }
             
             crate::opcodes::Opcode::Create => {
-                // Check if we're in static context (STATICCALL)
-                if self.static_context {
-                    return Err(EvmError::Unknown("CREATE not allowed in static context".to_string()));
-                }
-                
                 // CREATE opcode: value, offset, size
-                let value = self.stack.pop()?;
-                let offset = self.stack.pop()?;
-                let size = self.stack.pop()?;
-                
-                // Read the initcode from memory
-                let offset_usize = offset.as_usize();
-                let size_usize = size.as_usize();
-                let initcode = self.memory.read(offset_usize, size_usize)?;
-                
-                // Check initcode length (must be <= 49152 bytes according to spec)
-                if initcode.len() > 49152 {
-                    self.stack.push(Word::zero())?; // Return 0 for failure
-                    return Ok(());
-                }
-                
-                // Generate a deterministic address based on the caller address and nonce
-                // Ethereum CREATE uses keccak256(rlp.encode([sender, nonce]))
-                // For now, we'll use a simplified version since we don't have RLP encoding
-                // but we'll use proper Keccak-256 hashing
-                let mut address_data = Vec::new();
-                address_data.extend_from_slice(&self.address);
-                // In a real implementation, this would be the nonce, but we don't have access to it
-                // So we'll use a placeholder value (0) for now
-                address_data.extend_from_slice(&[0u8; 12]); // Pad to 32 bytes
-                
-                // Use proper Keccak-256 hash for address generation
-                use sha3::{Digest, Keccak256};
-                let mut hasher = Keccak256::new();
-                hasher.update(&address_data);
-                let result = hasher.finalize();
-                
-                // Convert the 32-byte hash result to a 20-byte address (take last 20 bytes)
-                let mut new_address = [0u8; 20];
-                for i in 0..20 {
-                    new_address[i] = result[result.len() - 20 + i];
-                }
-                
-                // Create the address word for the stack
-                let mut padded_address = vec![0u8; 32];
-                for (i, &byte) in new_address.iter().enumerate() {
-                    padded_address[32 - 20 + i] = byte;
-                }
-                let address_word = Word::from_big_endian(&padded_address);
-                
-                // Execute the initcode to get the contract code
-                // We need to create a new EVM instance to execute the initcode
-                let mut init_config = self.config.clone();
-                init_config.transaction.to = [0u8; 20]; // Contract creation
-                init_config.transaction.from = self.address;
-                init_config.transaction.value = value;
-                init_config.transaction.data = initcode.clone();
-                
-                // Create a new EVM state for executing the initcode
-                let mut init_state = EvmState::new(initcode.clone(), init_config);
-                init_state.storage = self.storage.clone(); // Share storage context
-                
-                // Execute the initcode until it halts
-                while init_state.status() == crate::state::ExecutionStatus::Running {
-                    if let Err(_) = init_state.step() {
-                        // On error, execution stops and returns failure
-                        init_state.reverted = true;
-                        break;
-                    }
-                }
-                
-                // Get the result and use the return data as the contract code
-                let result = init_state.result();
-                let contract_code = if result.success && !result.return_data.is_empty() {
-                    result.return_data
-                } else {
-                    // If execution failed or no return data, use empty code
-                    Vec::new()
-                };
-                
-                // If the initcode execution failed (reverted), return 0 to indicate failure
-                if !result.success {
-                    self.stack.push(Word::zero())?;
-                    return Ok(());
-                }
-                
-                // Add the new contract account to the test state with the actual code
-                 if let Some(ref test_state) = self.config.test_state {
-                     let mut test_state_borrowed = test_state.borrow_mut();
-                     let address_str = format!("0x{:040x}", address_word);
-                     test_state_borrowed.accounts.insert(address_str.clone(), crate::types::AccountState {
-                          balance: Some(format!("0x{:x}", value)),
-                          code: Some(crate::types::Code {
-                              asm: None,
-                              bin: contract_code.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
-                          }),
-                      });
-                  }
-                
-                // Push the new contract address onto the stack
-                self.stack.push(address_word)?;
-                
-                Ok(())
+                let args = self.stack.pop_n(3)?;
+                let (value, offset, size) = (args[0], args[1], args[2]);
+                self.create_contract(value, offset, size, None)
+            }
+            
+            crate::opcodes::Opcode::Create2 => {
+                // CREATE2 opcode: value, offset, size, salt
+                let args = self.stack.pop_n(4)?;
+                let (value, offset, size, salt) = (args[0], args[1], args[2], args[3]);
+                self.create_contract(value, offset, size, Some(salt))
             }
             
             crate::opcodes::Opcode::Call => {
-                // Check if we're in static context (STATICCALL)
-                if self.static_context {
-                    return Err(EvmError::Unknown("CALL not allowed in static context".to_string()));
+                // CALL opcode: gas, address, value, argsOffset, argsSize, retOffset, retSize
+                let args = self.stack.pop_n(7)?;
+                let (gas, address_bytes, value, args_offset, args_size, ret_offset, ret_size) = (args[0], args[1], args[2], args[3], args[4], args[5], args[6]);
+                
+                // A static frame may still call, but not transfer value
+                if self.static_context && !value.is_zero() {
+                    return Err(EvmError::Unknown("CALL with value not allowed in static context".to_string()));
                 }
                 
-                // CALL opcode: gas, address, value, argsOffset, argsSize, retOffset, retSize
-                let gas = self.stack.pop()?;
-                let address_bytes = self.stack.pop()?;
-                let value = self.stack.pop()?;
-                let args_offset = self.stack.pop()?;
-                let args_size = self.stack.pop()?;
-                let ret_offset = self.stack.pop()?;
-                let ret_size = self.stack.pop()?;
-                
-                // Convert address from Word to Address (20 bytes)
-                // Take the rightmost 20 bytes (low-order) of the 256-bit Word in big-endian order
-                let mut address = [0u8; 20];
-                for i in 0..20 {
-                    address[i] = address_bytes.byte(19 - i);
+                let address = word_to_address(address_bytes);
+                self.charge_account_access(address)?;
+                let new_account = self
+                    .config
+                    .test_state
+                    .as_ref()
+                    .is_some_and(|world| world.borrow().is_empty(&address));
+                self.gas_tracker.consume(crate::gas::call_value_cost(!value.is_zero(), new_account))?;
+                
+                // Extract call data from memory
+                let (call_data, ret_region) = self.prepare_call_memory(args_offset, args_size, ret_offset, ret_size)?;
+                
+                // Precompiled contracts run natively instead of executing code
+                if crate::precompiles::is_precompile(&address) {
+                    return self.call_precompile(&address, gas, value, call_data, ret_region);
                 }
                 
                 // Create consistent address string for lookups
@@ -1574,17 +1652,7 @@ impl EvmState {
                     let test_state_borrowed = test_state.borrow();
                     if let Some(account) = test_state_borrowed.accounts.get(&address_str) {
                         if let Some(code) = &account.code {
-                            // Convert hex string to bytes
-                            let mut code_bytes = Vec::new();
-                            let hex = &code.bin;
-                            for i in (0..hex.len()).step_by(2) {
-                                if i + 1 < hex.len() {
-                                    if let Ok(byte) = u8::from_str_radix(&hex[i..i+2], 16) {
-                                        code_bytes.push(byte);
-                                    }
-                                }
-                            }
-                            code_bytes
+                            crate::util::parse_hex(&code.bin).unwrap_or_default()
                         } else {
                             vec![]
                         }
@@ -1595,27 +1663,48 @@ impl EvmState {
                     vec![]
                 };
                 
-                // If no code, return failure
-                if contract_code.is_empty() {
-                    self.stack.push(Word::from(0))?; // Failure
-                    return Ok(());
-                }
+                // A target without code still runs: it is a plain value transfer that succeeds
+                
+                // The forwarded gas is paid up front and whatever is left comes back;
+                // a value transfer adds a free stipend on top
+                let gas_limit = self.call_gas(gas);
+                self.gas_tracker.consume(gas_limit)?;
+                let stipend = if value.is_zero() { 0 } else { crate::gas::GAS_CALL_STIPEND };
                 
                 // Create a new EVM instance to execute the contract
                 let mut call_config = self.config.clone();
                 call_config.transaction.to = address;
                 call_config.transaction.from = self.address;
                 call_config.transaction.value = value;
-                
-                // Extract call data from memory
-                let args_offset_usize = args_offset.as_usize();
-                let args_size_usize = args_size.as_usize();
-                let call_data = self.memory.read(args_offset_usize, args_size_usize)?;
+                call_config.static_context = self.static_context;
                 call_config.transaction.data = call_data;
                 
-                // Execute the contract
-                let evm = crate::vm::Evm::new(call_config);
-                let result = evm.execute(contract_code);
+                // Move the value to the callee; a caller that cannot afford it fails
+                // the call without running any code, and keeps the forwarded gas
+                if !self.transfer(self.address, address, value) {
+                    self.gas_tracker.return_gas(gas_limit);
+                    self.return_data.clear();
+                    self.stack.push(Word::zero())?;
+                    return Ok(());
+                }
+                
+                // Execute the contract against the same world state, so the callee
+                // (or a re-entrant call back into us) sees every SSTORE made so far
+                self.flush_storage();
+                let mut call_state = EvmState::with_gas(contract_code, call_config, gas_limit + stipend);
+                call_state.run();
+                let result = call_state.result();
+                if result.success {
+                    self.keep_frame(&mut call_state);
+                    self.write_storage(&call_state.storage);
+                }
+                self.reload_storage();
+                self.gas_tracker.return_gas(result.gas_remaining);
+                
+                // Give the value back if the call failed
+                if !result.success {
+                    self.transfer(address, self.address, value);
+                }
                 
                 // Push success/failure (1 for success, 0 for failure)
                 if result.success {
@@ -1625,104 +1714,134 @@ impl EvmState {
                 }
                 
                 // Always copy return data to memory if specified (even on revert)
-                let ret_offset_usize = ret_offset.as_usize();
-                let ret_size_usize = ret_size.as_usize();
-                let return_data = result.return_data;
+                self.write_call_output(ret_region, &result.return_data)?;
                 
                 // Update the current state's return_data field for RETURNDATASIZE
-                self.return_data = return_data.clone();
-                
-                for i in 0..ret_size_usize.min(return_data.len()) {
-                    self.memory.write(ret_offset_usize + i, &[return_data[i]])?;
-                }
+                self.return_data = result.return_data;
                 
                 Ok(())
             }
             
-            crate::opcodes::Opcode::Delegatecall => {
-                // Check if we're in static context (STATICCALL)
-                if self.static_context {
-                    return Err(EvmError::Unknown("DELEGATECALL not allowed in static context".to_string()));
-                }
+            crate::opcodes::Opcode::Callcode => {
+                // CALLCODE opcode: gas, address, value, argsOffset, argsSize, retOffset, retSize
+                let args = self.stack.pop_n(7)?;
+                let (gas, address_bytes, value, args_offset, args_size, ret_offset, ret_size) = (args[0], args[1], args[2], args[3], args[4], args[5], args[6]);
                 
-                // DELEGATECALL opcode: gas, address, argsOffset, argsSize, retOffset, retSize
-                let gas = self.stack.pop()?;
-                let address_bytes = self.stack.pop()?;
-                let args_offset = self.stack.pop()?;
-                let args_size = self.stack.pop()?;
-                let ret_offset = self.stack.pop()?;
-                let ret_size = self.stack.pop()?;
-                
-                // Convert address from Word to Address (20 bytes)
-                // Take the rightmost 20 bytes (low-order) of the 256-bit Word in big-endian order
-                let mut address = [0u8; 20];
-                for i in 0..20 {
-                    address[i] = address_bytes.byte(19 - i);
+                // The value stays with the current contract, so no account is ever created
+                let address = word_to_address(address_bytes);
+                self.charge_account_access(address)?;
+                self.gas_tracker.consume(crate::gas::call_value_cost(!value.is_zero(), false))?;
+                
+                // Extract call data from memory
+                let (call_data, ret_region) = self.prepare_call_memory(args_offset, args_size, ret_offset, ret_size)?;
+                
+                let contract_code = self
+                    .config
+                    .test_state
+                    .as_ref()
+                    .map(|world| world.borrow().code(&address))
+                    .unwrap_or_default();
+                
+                // The value moves from the current contract to itself, but it must still afford it
+                let affordable = self
+                    .config
+                    .test_state
+                    .as_ref()
+                    .is_none_or(|world| world.borrow().balance(&self.address) >= value);
+                if !affordable {
+                    self.return_data.clear();
+                    self.stack.push(Word::zero())?;
+                    return Ok(());
                 }
                 
-                // Get the contract code from test state
-                let contract_code = if let Some(test_state) = &self.config.test_state {
-                    let test_state_borrowed = test_state.borrow();
-                    if let Some(account) = test_state_borrowed.accounts.get(&format!("0x{:x}", address_bytes)) {
-                        if let Some(code) = &account.code {
-                            // Convert hex string to bytes
-                            let mut code_bytes = Vec::new();
-                            let hex = &code.bin;
-                            for i in (0..hex.len()).step_by(2) {
-                                if i + 1 < hex.len() {
-                                    if let Ok(byte) = u8::from_str_radix(&hex[i..i+2], 16) {
-                                        code_bytes.push(byte);
-                                    }
-                                }
-                            }
-                            code_bytes
-                        } else {
-                            vec![]
-                        }
-                    } else {
-                        vec![]
-                    }
-                } else {
-                    vec![]
-                };
+                // The forwarded gas is paid up front and whatever is left comes back;
+                // a value transfer adds a free stipend on top
+                let gas_limit = self.call_gas(gas);
+                self.gas_tracker.consume(gas_limit)?;
+                let stipend = if value.is_zero() { 0 } else { crate::gas::GAS_CALL_STIPEND };
                 
-                // If no code, return failure
-                if contract_code.is_empty() {
-                    self.stack.push(Word::from(0))?; // Failure
-                    return Ok(());
+                // CALLCODE runs the other account's code as the current contract,
+                // called by the current contract with the given value
+                let mut call_config = self.config.clone();
+                call_config.transaction.to = self.address;
+                call_config.transaction.from = self.address;
+                call_config.transaction.value = value;
+                call_config.transaction.data = call_data;
+                call_config.static_context = self.static_context;
+                
+                let mut callcode_state = EvmState::with_gas(contract_code, call_config, gas_limit + stipend);
+                callcode_state.storage = self.storage.clone(); // Share storage context
+                callcode_state.run();
+                
+                // Keep the callee's writes only if it succeeded; a failed callee
+                // leaves our storage as it was before the call
+                let result = callcode_state.result();
+                if result.success {
+                    self.keep_frame(&mut callcode_state);
+                    self.storage = callcode_state.storage;
                 }
+                self.gas_tracker.return_gas(result.gas_remaining);
+                
+                // Push success/failure (1 for success, 0 for failure)
+                self.stack.push(Word::from(result.success as u8))?;
+                
+                // Always copy return data to memory if specified (even on revert)
+                self.write_call_output(ret_region, &result.return_data)?;
+                
+                // Update the current state's return_data field for RETURNDATASIZE
+                self.return_data = result.return_data;
+                
+                Ok(())
+            }
+            
+            crate::opcodes::Opcode::Delegatecall => {
+                // DELEGATECALL opcode: gas, address, argsOffset, argsSize, retOffset, retSize
+                let args = self.stack.pop_n(6)?;
+                let (gas, address_bytes, args_offset, args_size, ret_offset, ret_size) = (args[0], args[1], args[2], args[3], args[4], args[5]);
+                self.charge_account_access(word_to_address(address_bytes))?;
                 
                 // Extract call data from memory
-                let args_offset_usize = args_offset.as_usize();
-                let args_size_usize = args_size.as_usize();
-                let call_data = self.memory.read(args_offset_usize, args_size_usize)?;
+                let (call_data, ret_region) = self.prepare_call_memory(args_offset, args_size, ret_offset, ret_size)?;
+                
+                // A target without code still runs, and succeeds with no output
+                let contract_code = self
+                    .config
+                    .test_state
+                    .as_ref()
+                    .map(|world| world.borrow().code(&word_to_address(address_bytes)))
+                    .unwrap_or_default();
                 
                 // Create a new EVM instance to execute the contract
-                // DELEGATECALL preserves the transaction context (caller, origin, address)
+                // DELEGATECALL preserves the transaction context (caller, value, address).
+                // Since self.address is already the storage context of any enclosing
+                // DELEGATECALL, the whole delegate chain runs in the original contract.
                 let mut call_config = self.config.clone();
-                call_config.transaction.to = address;
-                // Keep the original caller, origin, and address
+                call_config.transaction.to = self.address;
                 call_config.transaction.from = self.caller;
+                call_config.transaction.value = self.callvalue;
                 call_config.transaction.data = call_data.clone();
+                call_config.static_context = self.static_context;
+                
+                // The forwarded gas is paid up front and whatever is left comes back
+                let gas_limit = self.call_gas(gas);
+                self.gas_tracker.consume(gas_limit)?;
                 
                 // For DELEGATECALL, we need to share the storage context
                 // Create a new EvmState but with the same storage
-                let mut delegate_state = EvmState::new(contract_code.clone(), call_config.clone());
+                let mut delegate_state = EvmState::with_gas(contract_code.clone(), call_config.clone(), gas_limit);
                 delegate_state.storage = self.storage.clone(); // Share storage context
-                delegate_state.address = self.address; // Keep the same address
                 
                 // Execute the contract in the delegate state
-                while delegate_state.status() == crate::state::ExecutionStatus::Running {
-                    if let Err(_) = delegate_state.step() {
-                        // On error, execution stops and returns failure
-                        delegate_state.reverted = true;
-                        break;
-                    }
-                }
+                delegate_state.run();
                 
-                // Get the result and update our storage
+                // Keep the callee's writes only if it succeeded; a failed callee
+                // leaves our storage as it was before the call
                 let result = delegate_state.result();
-                self.storage = delegate_state.storage; // Update our storage with any changes
+                if result.success {
+                    self.keep_frame(&mut delegate_state);
+                    self.storage = delegate_state.storage;
+                }
+                self.gas_tracker.return_gas(result.gas_remaining);
                 
                 // Push success/failure (1 for success, 0 for failure)
                 if result.success {
@@ -1732,72 +1851,32 @@ impl EvmState {
                 }
                 
                 // Always copy return data to memory if specified (even on revert)
-                let ret_offset_usize = ret_offset.as_usize();
-                let ret_size_usize = ret_size.as_usize();
-                let return_data = result.return_data;
+                self.write_call_output(ret_region, &result.return_data)?;
                 
                 // Update the current state's return_data field for RETURNDATASIZE
-                self.return_data = return_data.clone();
-                
-                for i in 0..ret_size_usize.min(return_data.len()) {
-                    self.memory.write(ret_offset_usize + i, &[return_data[i]])?;
-                }
+                self.return_data = result.return_data;
                 
                 Ok(())
             }
             
             crate::opcodes::Opcode::Staticcall => {
                 // STATICCALL opcode: gas, address, argsOffset, argsSize, retOffset, retSize
-                let gas = self.stack.pop()?;
-                let address_bytes = self.stack.pop()?;
-                let args_offset = self.stack.pop()?;
-                let args_size = self.stack.pop()?;
-                let ret_offset = self.stack.pop()?;
-                let ret_size = self.stack.pop()?;
-                
-                // Convert address from Word to Address (20 bytes)
-                // Take the rightmost 20 bytes (low-order) of the 256-bit Word in big-endian order
-                let mut address = [0u8; 20];
-                for i in 0..20 {
-                    address[i] = address_bytes.byte(19 - i);
-                }
+                let args = self.stack.pop_n(6)?;
+                let (gas, address_bytes, args_offset, args_size, ret_offset, ret_size) = (args[0], args[1], args[2], args[3], args[4], args[5]);
                 
-                // Get the contract code from test state
-                let contract_code = if let Some(test_state) = &self.config.test_state {
-                    let test_state_borrowed = test_state.borrow();
-                    if let Some(account) = test_state_borrowed.accounts.get(&format!("0x{:x}", address_bytes)) {
-                        if let Some(code) = &account.code {
-                            // Convert hex string to bytes
-                            let mut code_bytes = Vec::new();
-                            let hex = &code.bin;
-                            for i in (0..hex.len()).step_by(2) {
-                                if i + 1 < hex.len() {
-                                    if let Ok(byte) = u8::from_str_radix(&hex[i..i+2], 16) {
-                                        code_bytes.push(byte);
-                                    }
-                                }
-                            }
-                            code_bytes
-                        } else {
-                            vec![]
-                        }
-                    } else {
-                        vec![]
-                    }
-                } else {
-                    vec![]
-                };
-                
-                // If no code, return failure
-                if contract_code.is_empty() {
-                    self.stack.push(Word::from(0))?; // Failure
-                    return Ok(());
-                }
+                let address = word_to_address(address_bytes);
+                self.charge_account_access(address)?;
                 
                 // Extract call data from memory
-                let args_offset_usize = args_offset.as_usize();
-                let args_size_usize = args_size.as_usize();
-                let call_data = self.memory.read(args_offset_usize, args_size_usize)?;
+                let (call_data, ret_region) = self.prepare_call_memory(args_offset, args_size, ret_offset, ret_size)?;
+                
+                // A target without code still runs, and succeeds with no output
+                let contract_code = self
+                    .config
+                    .test_state
+                    .as_ref()
+                    .map(|world| world.borrow().code(&address))
+                    .unwrap_or_default();
                 
                 // Create a new EVM instance to execute the contract
                 // STATICCALL disables state modifications
@@ -1805,26 +1884,24 @@ impl EvmState {
                 call_config.transaction.to = address;
                 call_config.transaction.from = self.address;
                 call_config.transaction.data = call_data;
+                call_config.static_context = true; // Inherited by any call made from within
                 
-                // For STATICCALL, we need to share the storage context
-                // Create a new EvmState but with the same storage
-                let mut static_state = EvmState::new(contract_code, call_config);
-                static_state.storage = self.storage.clone(); // Share storage context
-                static_state.address = self.address; // Keep the same address
-                static_state.static_context = true; // Set static context for the call
+                // The forwarded gas is paid up front and whatever is left comes back
+                let gas_limit = self.call_gas(gas);
+                self.gas_tracker.consume(gas_limit)?;
                 
-                // Execute the contract in the static state
-                while static_state.status() == crate::state::ExecutionStatus::Running {
-                    if let Err(e) = static_state.step() {
-                        // On error, execution stops and returns failure
-                        static_state.reverted = true;
-                        break;
-                    }
-                }
+                // The callee runs on its own storage, loaded from the world state;
+                // ours is flushed first so a call back into us sees every SSTORE so far
+                self.flush_storage();
+                let mut static_state = EvmState::with_gas(contract_code, call_config, gas_limit);
                 
-                // Get the result and update our storage
+                // Execute the contract in the static state
+                static_state.run();
                 let result = static_state.result();
-                self.storage = static_state.storage; // Update our storage with any changes
+                if result.success {
+                    self.keep_frame(&mut static_state);
+                }
+                self.gas_tracker.return_gas(result.gas_remaining);
                 
                 // Push success/failure (1 for success, 0 for failure)
                 if result.success {
@@ -1834,16 +1911,10 @@ impl EvmState {
                 }
                 
                 // Always copy return data to memory if specified (even on revert)
-                let ret_offset_usize = ret_offset.as_usize();
-                let ret_size_usize = ret_size.as_usize();
-                let return_data = result.return_data;
+                self.write_call_output(ret_region, &result.return_data)?;
                 
                 // Update the current state's return_data field for RETURNDATASIZE
-                self.return_data = return_data.clone();
-                
-                for i in 0..ret_size_usize.min(return_data.len()) {
-                    self.memory.write(ret_offset_usize + i, &[return_data[i]])?;
-                }
+                self.return_data = result.return_data;
                 
                 Ok(())
             }
@@ -1855,11 +1926,242 @@ impl EvmState {
         }
     }
 
-    /// Check if an opcode is a jump operation
-    fn is_jump_opcode(&self, opcode: crate::opcodes::Opcode) -> bool {
-        matches!(opcode, crate::opcodes::Opcode::Jump | crate::opcodes::Opcode::Jumpi)
+    /// Run initcode from memory and deploy the returned code (CREATE and CREATE2)
+    ///
+    /// Pushes the new address, or zero if the initcode failed. With a salt the
+    /// address is derived as in CREATE2 from the hash of the exact initcode bytes.
+    fn create_contract(&mut self, value: Word, offset: Word, size: Word, salt: Option<Word>) -> Result<(), EvmError> {
+        // Check if we're in static context (STATICCALL)
+        if self.static_context {
+            return Err(EvmError::Unknown("CREATE not allowed in static context".to_string()));
+        }
+        
+        // The base cost was charged with the opcode; memory expansion, the
+        // initcode word cost and CREATE2's hashing cost are charged before any initcode runs
+        let (offset_usize, size_usize) = self.charge_memory_expansion(offset, size)?;
+        if self.config.is_eip_enabled(3860, crate::types::Hardfork::Shanghai) {
+            self.gas_tracker.consume(crate::gas::initcode_cost(size_usize))?;
+        }
+        if salt.is_some() {
+            let words = size_usize.div_ceil(32) as crate::types::Gas;
+            self.gas_tracker.consume(crate::gas::GAS_KECCAK_WORD * words)?;
+        }
+
+        // Read the initcode from memory
+        let initcode = self.memory.read(offset_usize, size_usize)?;
+        
+        // Since Shanghai, init code is limited to MAX_INITCODE_SIZE bytes
+        if initcode.len() > MAX_INITCODE_SIZE && self.config.is_eip_enabled(3860, crate::types::Hardfork::Shanghai) {
+            self.stack.push(Word::zero())?; // Return 0 for failure
+            return Ok(());
+        }
+        
+        // Both CREATE and CREATE2 bump the sender's nonce, even if the initcode fails
+        self.journal_account(self.address);
+        let nonce = self
+            .config
+            .test_state
+            .as_ref()
+            .map(|test_state| test_state.borrow_mut().increment_nonce(&self.address))
+            .unwrap_or_default();
+
+        let new_address = match salt {
+            Some(salt) => crate::util::create2_address(&self.address, salt, &initcode),
+            None => crate::util::create_address(&self.address, nonce),
+        };
+        
+        let address_word = address_to_word(new_address);
+        
+        // Execute the initcode to get the contract code
+        // We need to create a new EVM instance to execute the initcode
+        let mut init_config = self.config.clone();
+        init_config.transaction.to = new_address; // The init code runs as the new contract
+        init_config.transaction.from = self.address;
+        init_config.transaction.value = value;
+        init_config.transaction.data = initcode.clone();
+        let gas_limit = crate::gas::all_but_one_64th(self.gas_tracker.remaining());
+        init_config.gas_limit = gas_limit;
+        init_config.initial_gas_refund = 0;
+        
+        // Create a new EVM state for executing the initcode, on the new contract's
+        // own storage; ours is flushed first so a call back into us sees every SSTORE so far
+        self.flush_storage();
+        let mut init_state = EvmState::new(initcode.clone(), init_config);
+        
+        // Execute the initcode until it halts
+        init_state.run();
+        self.reload_storage();
+        
+        // Get the result and use the return data as the contract code
+        let result = init_state.result();
+        
+        // Code beyond the size limit fails the creation and burns all of its gas
+        if result.success && exceeds_code_size(&self.config, &result.return_data) {
+            self.gas_tracker.consume(gas_limit)?;
+            self.stack.push(Word::zero())?;
+            return Ok(());
+        }
+        
+        self.gas_tracker.consume(result.gas_used)?;
+        let contract_code = if result.success && !result.return_data.is_empty() {
+            result.return_data
+        } else {
+            // If execution failed or no return data, use empty code
+            Vec::new()
+        };
+        
+        // If the initcode execution failed (reverted), return 0 to indicate failure
+        if !result.success {
+            self.stack.push(Word::zero())?;
+            return Ok(());
+        }
+        
+        // Add the new contract account to the test state with the actual code
+        self.keep_frame(&mut init_state);
+        self.journal_account(new_address);
+        if let Some(ref test_state) = self.config.test_state {
+            let mut test_state_borrowed = test_state.borrow_mut();
+            let address_str = format!("0x{:040x}", address_word);
+            test_state_borrowed.accounts.insert(address_str.clone(), crate::types::AccountState {
+                balance: Some(format!("0x{:x}", value)),
+                // New contracts start with nonce 1 (EIP-161)
+                nonce: Some("0x1".to_string()),
+                code: Some(crate::types::Code {
+                    asm: None,
+                    bin: contract_code.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+                }),
+                ..Default::default()
+            });
+        }
+        
+        // Keep what the constructor stored
+        self.write_storage(&init_state.storage);
+        
+        // Push the new contract address onto the stack
+        self.stack.push(address_word)?;
+        
+        Ok(())
+    }
+
+    /// Call a precompiled contract and push whether it succeeded
+    ///
+    /// The precompile gets the requested gas, capped at all but one 64th of
+    /// what remains; its cost is charged to this context, or all of that gas if it fails.
+    fn call_precompile(&mut self, address: &Address, gas: Word, value: Word, input: Vec<u8>, ret_region: (usize, usize)) -> Result<(), EvmError> {
+        let gas_limit = self.call_gas(gas);
+
+        if !self.transfer(self.address, *address, value) {
+            self.return_data.clear();
+            self.stack.push(Word::zero())?;
+            return Ok(());
+        }
+
+        match crate::precompiles::run(address, &input, gas_limit) {
+            Some(Ok((gas_used, output))) => {
+                self.gas_tracker.consume(gas_used)?;
+                self.write_call_output(ret_region, &output)?;
+                self.return_data = output;
+                self.stack.push(Word::one())?;
+            }
+            _ => {
+                self.gas_tracker.consume(gas_limit)?;
+                self.transfer(*address, self.address, value);
+                self.return_data.clear();
+                self.stack.push(Word::zero())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Gas forwarded to a sub-context: the requested `gas`, capped at all but
+    /// one 64th of what remains (EIP-150)
+    fn call_gas(&self, gas: Word) -> crate::types::Gas {
+        let available = crate::gas::all_but_one_64th(self.gas_tracker.remaining());
+        if gas < Word::from(available) { gas.as_u64() } else { available }
     }
-    
+
+    /// Charge the gas for expanding memory to cover `size` bytes at `offset`
+    ///
+    /// Returns the region as `usize`s. A zero-size region is free and never
+    /// expands memory, whatever its offset.
+    fn charge_memory_expansion(&mut self, offset: Word, size: Word) -> Result<(usize, usize), EvmError> {
+        let (offset, size) = Self::memory_region(offset, size)?;
+        self.gas_tracker.consume(crate::gas::memory_expansion_cost(self.memory.size_words(), offset, size))?;
+        Ok((offset, size))
+    }
+
+    /// Charge a copy of `size` bytes to memory at `offset`: memory expansion and
+    /// the per-word copy cost, paid before anything is allocated
+    fn charge_copy(&mut self, offset: Word, size: Word) -> Result<(usize, usize), EvmError> {
+        let (offset, size) = self.charge_memory_expansion(offset, size)?;
+        self.gas_tracker.consume(crate::gas::copy_cost(size))?;
+        Ok((offset, size))
+    }
+
+    /// `size` bytes of `source` from `offset`, zero-padded past its end
+    fn copy_padded(source: &[u8], offset: Word, size: usize) -> Vec<u8> {
+        let mut data = vec![0u8; size];
+        let start = crate::util::word_to_usize(offset).unwrap_or(usize::MAX).min(source.len());
+        let available = &source[start..];
+        let len = available.len().min(size);
+        data[..len].copy_from_slice(&available[..len]);
+        data
+    }
+
+    /// Convert a memory region to `usize`s; a zero-size region is always (0, 0)
+    fn memory_region(offset: Word, size: Word) -> Result<(usize, usize), EvmError> {
+        if size.is_zero() {
+            return Ok((0, 0));
+        }
+        // Regions this large could never be paid for
+        match (crate::util::word_to_usize(offset), crate::util::word_to_usize(size)) {
+            (Some(offset), Some(size)) if offset <= u32::MAX as usize && size <= u32::MAX as usize => Ok((offset, size)),
+            _ => Err(EvmError::OutOfGas),
+        }
+    }
+
+    /// Set up memory for a call: charge and expand memory for the larger of the
+    /// argument and return regions, and read the call data
+    ///
+    /// Returns the call data and the return region as `usize`s.
+    fn prepare_call_memory(&mut self, args_offset: Word, args_size: Word, ret_offset: Word, ret_size: Word) -> Result<(Vec<u8>, (usize, usize)), EvmError> {
+        let (args_offset, args_size) = Self::memory_region(args_offset, args_size)?;
+        let (ret_offset, ret_size) = Self::memory_region(ret_offset, ret_size)?;
+        let end = (args_offset + args_size).max(ret_offset + ret_size);
+        self.gas_tracker.consume(crate::gas::memory_expansion_cost(self.memory.size_words(), 0, end))?;
+
+        if end > 0 {
+            self.memory.expand(end)?;
+        }
+
+        let call_data = self.memory.read(args_offset, args_size)?;
+        Ok((call_data, (ret_offset, ret_size)))
+    }
+
+    /// Copy as much of a call's return data as fits into the return region,
+    /// zero-filling the rest of it when the output is shorter
+    fn write_call_output(&mut self, (ret_offset, ret_size): (usize, usize), output: &[u8]) -> Result<(), EvmError> {
+        let mut region = vec![0u8; ret_size];
+        let copied = ret_size.min(output.len());
+        region[..copied].copy_from_slice(&output[..copied]);
+        self.memory.write(ret_offset, &region)
+    }
+
+    /// Check a JUMP or JUMPI destination and convert it to a code position
+    ///
+    /// Destinations past the end of the code, including ones too large for
+    /// `usize`, are rejected before conversion.
+    fn jump_target(&self, destination: Word) -> Result<usize, EvmError> {
+        if destination >= Word::from(self.code.len()) {
+            return Err(EvmError::InvalidJumpDestination);
+        }
+        let position = destination.as_usize();
+        if !self.is_valid_jump_destination(position) {
+            return Err(EvmError::InvalidJumpDestination);
+        }
+        Ok(position)
+    }
+
     /// Check if a position is a valid jump destination
     /// According to the Ethereum Yellow Paper, JUMP destinations must be at valid instruction boundaries
     fn is_valid_jump_destination(&self, position: usize) -> bool {
@@ -1879,7 +2181,7 @@ impl EvmState {
             let opcode = self.code[current_pos];
             
             // Handle PUSH instructions (they have data that's not valid instruction boundaries)
-            if opcode >= 0x60 && opcode <= 0x7f { // PUSH1 to PUSH32
+            if (0x60..=0x7f).contains(&opcode) { // PUSH1 to PUSH32
                 let data_size = (opcode - 0x60 + 1) as usize;
                 current_pos += 1 + data_size; // Skip opcode + data
             } else {
@@ -1901,18 +2203,38 @@ impl EvmState {
         }
     }
 
+    /// Memory as the program sees it, padded with zeros to whole words
+    fn final_memory(&self) -> Vec<u8> {
+        let mut memory = self.memory.as_slice().to_vec();
+        memory.resize(memory.len().max(self.memory.size_words() * 32), 0);
+        memory
+    }
+
     /// Get the final result of execution
     pub fn result(&self) -> crate::types::EvmResult {
         crate::types::EvmResult {
             success: !self.reverted,
             gas_used: self.gas_tracker.gas_used(),
+            gas_remaining: self.gas_tracker.remaining(),
+            gas_refund: self.gas_tracker.capped_refund(),
+            outcome: self.outcome.clone(),
             stack: self.stack.data().iter().rev().cloned().collect(),
-            return_data: self.return_data.clone(),
+            return_data: self.output.clone(),
             logs: self.logs.clone(),
+            final_memory: self.final_memory(),
+            gas_profile: self.gas_profile.clone(),
+            created_address: None,
+            deployed_code: None,
         }
     }
 }
 
+/// Whether code returned by init code is too large to deploy (EIP-170, from Spurious
+/// Dragon, which predates Byzantium)
+pub(crate) fn exceeds_code_size(config: &EvmConfig, code: &[u8]) -> bool {
+    code.len() > MAX_CODE_SIZE && config.is_eip_enabled(170, crate::types::Hardfork::Byzantium)
+}
+
 /// Execution status of the EVM
 #[derive(Debug, Clone, PartialEq)]
 pub enum ExecutionStatus {
@@ -0,0 +1,352 @@
+use crate::types::{word_from_hex, word_to_address, AccountState, Address, Code, EvmConfig, Hardfork, Log, TestState, Word};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A single test from the `ethereum/tests` GeneralStateTests format
+#[derive(Debug, Clone, Deserialize)]
+pub struct StateTest {
+    pub env: Env,
+    pub pre: HashMap<String, PreAccount>,
+    pub transaction: StateTransaction,
+    pub post: HashMap<String, Vec<PostState>>,
+}
+
+/// Block environment of a state test
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Env {
+    pub current_coinbase: String,
+    pub current_difficulty: Option<String>,
+    pub current_gas_limit: String,
+    pub current_number: String,
+    pub current_timestamp: String,
+    pub current_base_fee: Option<String>,
+    pub current_random: Option<String>,
+    pub current_excess_blob_gas: Option<String>,
+}
+
+/// Account in the pre-state of a state test
+#[derive(Debug, Clone, Deserialize)]
+pub struct PreAccount {
+    pub balance: String,
+    #[serde(default)]
+    pub nonce: Option<String>,
+    pub code: String,
+    #[serde(default)]
+    pub storage: HashMap<String, String>,
+}
+
+/// Transaction of a state test; `data`, `gasLimit` and `value` are indexed by the post entries
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateTransaction {
+    pub data: Vec<String>,
+    pub gas_limit: Vec<String>,
+    pub gas_price: Option<String>,
+    pub sender: Option<String>,
+    pub to: String,
+    pub value: Vec<String>,
+}
+
+/// Expected outcome for one combination of transaction indexes
+#[derive(Debug, Clone, Deserialize)]
+pub struct PostState {
+    pub indexes: Indexes,
+    pub logs: String,
+    /// Optional explicit expectations for accounts in the post-state.
+    /// The state root in `hash` is not checked.
+    #[serde(default)]
+    pub state: HashMap<String, PostAccount>,
+}
+
+/// Indexes into the transaction's `data`, `gasLimit` and `value` arrays
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Indexes {
+    pub data: usize,
+    pub gas: usize,
+    pub value: usize,
+}
+
+/// Expected account fields in the post-state
+#[derive(Debug, Clone, Deserialize)]
+pub struct PostAccount {
+    pub balance: Option<String>,
+    #[serde(default)]
+    pub storage: HashMap<String, String>,
+}
+
+/// Parse a file of state tests, keyed by test name
+pub fn parse_tests(json: &str) -> Result<HashMap<String, StateTest>, String> {
+    serde_json::from_str(json).map_err(|e| e.to_string())
+}
+
+fn parse_word(value: &str) -> Result<Word, String> {
+    word_from_hex(value).map_err(|e| format!("Invalid number {}: {}", value, e))
+}
+
+fn parse_bytes(value: &str) -> Result<Vec<u8>, String> {
+    crate::util::parse_hex(value).map_err(|e| format!("Invalid hex {}: {}", value, e))
+}
+
+fn parse_address(value: &str) -> Result<Address, String> {
+    Ok(word_to_address(parse_word(value)?))
+}
+
+/// RLP-encode a byte string
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut out = rlp_length(bytes.len(), 0x80);
+    out.extend_from_slice(bytes);
+    out
+}
+
+/// RLP-encode a list from its already encoded items
+fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload = items.concat();
+    let mut out = rlp_length(payload.len(), 0xc0);
+    out.extend_from_slice(&payload);
+    out
+}
+
+fn rlp_length(length: usize, offset: u8) -> Vec<u8> {
+    if length <= 55 {
+        return vec![offset + length as u8];
+    }
+    let length_bytes: Vec<u8> = length.to_be_bytes().iter().copied().skip_while(|&b| b == 0).collect();
+    let mut out = vec![offset + 55 + length_bytes.len() as u8];
+    out.extend_from_slice(&length_bytes);
+    out
+}
+
+/// Compute the `logs` hash of a state test: keccak256 of the RLP-encoded log list
+pub fn logs_hash(logs: &[Log]) -> Word {
+    use sha3::{Digest, Keccak256};
+
+    let encoded = rlp_list(
+        &logs
+            .iter()
+            .map(|log| {
+                let topics = log
+                    .topics
+                    .iter()
+                    .map(|topic| {
+                        let mut bytes = [0u8; 32];
+                        topic.to_big_endian(&mut bytes);
+                        rlp_bytes(&bytes)
+                    })
+                    .collect::<Vec<_>>();
+                rlp_list(&[rlp_bytes(&log.address), rlp_list(&topics), rlp_bytes(&log.data)])
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    Word::from_big_endian(&Keccak256::digest(&encoded))
+}
+
+impl StateTest {
+    /// Build the world state from the test's pre-state
+    pub fn world_state(&self) -> Result<TestState, String> {
+        let mut accounts = HashMap::new();
+        for (address, account) in &self.pre {
+            let address = TestState::account_key(&parse_address(address)?);
+            accounts.insert(address, AccountState {
+                balance: Some(account.balance.clone()),
+                nonce: account.nonce.clone(),
+                code: Some(Code {
+                    asm: None,
+                    bin: account.code.trim_start_matches("0x").to_string(),
+                }),
+                storage: Some(account.storage.clone()),
+            });
+        }
+        Ok(TestState { accounts })
+    }
+
+    /// Build the EVM configuration for the transaction selected by `indexes`
+    pub fn config(&self, indexes: &Indexes) -> Result<EvmConfig, String> {
+        let tx = &self.transaction;
+        let mut config = EvmConfig {
+            coinbase: parse_address(&self.env.current_coinbase)?,
+            block_gas_limit: parse_word(&self.env.current_gas_limit)?,
+            block_number: parse_word(&self.env.current_number)?.low_u64(),
+            block_timestamp: parse_word(&self.env.current_timestamp)?.low_u64(),
+            ..Default::default()
+        };
+
+        if let Some(ref difficulty) = self.env.current_difficulty {
+            config.block_difficulty = parse_word(difficulty)?;
+        }
+        if let Some(ref random) = self.env.current_random {
+            config.block_prevrandao = parse_word(random)?;
+        }
+        if let Some(ref excess_blob_gas) = self.env.current_excess_blob_gas {
+            config.excess_blob_gas = parse_word(excess_blob_gas)?.low_u64();
+            config.block_blob_base_fee = config.blob_base_fee();
+        }
+        if let Some(ref base_fee) = self.env.current_base_fee {
+            config.block_base_fee = parse_word(base_fee)?;
+        }
+
+        if tx.to.is_empty() {
+            return Err("Contract creation transactions are not supported".to_string());
+        }
+        config.transaction.to = parse_address(&tx.to)?;
+        if let Some(ref sender) = tx.sender {
+            config.transaction.from = parse_address(sender)?;
+            config.origin = config.transaction.from;
+        }
+        if let Some(ref gas_price) = tx.gas_price {
+            config.transaction.gas_price = parse_word(gas_price)?;
+        }
+        let select = |values: &Vec<String>, index: usize, name: &str| {
+            values.get(index).cloned().ok_or_else(|| format!("Missing transaction {} at index {}", name, index))
+        };
+        config.transaction.data = parse_bytes(&select(&tx.data, indexes.data, "data")?)?;
+        config.transaction.value = parse_word(&select(&tx.value, indexes.value, "value")?)?;
+        config.gas_limit = parse_word(&select(&tx.gas_limit, indexes.gas, "gasLimit")?)?.low_u64();
+
+        config.test_state = Some(Rc::new(RefCell::new(self.world_state()?)));
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Run every post expectation of `fork`, returning one result per entry
+    ///
+    /// The transaction runs under `fork` with transaction semantics: intrinsic
+    /// gas, the sender's nonce and value, and the gas fee paid to the coinbase.
+    /// Logs are compared through the `logs` hash and accounts listed in the
+    /// optional `state` field are compared against the final world state.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::statetest::parse_tests;
+    ///
+    /// // The contract at 0x..1000 runs PUSH1 1 PUSH1 0 SSTORE
+    /// let json = r#"{
+    ///   "sstore": {
+    ///     "env": {
+    ///       "currentCoinbase": "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba",
+    ///       "currentDifficulty": "0x020000",
+    ///       "currentGasLimit": "0xff112233445566",
+    ///       "currentNumber": "0x01",
+    ///       "currentTimestamp": "0x03e8",
+    ///       "currentBaseFee": "0x0a"
+    ///     },
+    ///     "pre": {
+    ///       "0x0000000000000000000000000000000000001000": {
+    ///         "balance": "0x00", "code": "0x6001600055", "nonce": "0x00", "storage": {}
+    ///       },
+    ///       "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b": {
+    ///         "balance": "0x0de0b6b3a7640000", "code": "0x", "nonce": "0x00", "storage": {}
+    ///       }
+    ///     },
+    ///     "transaction": {
+    ///       "data": ["0x"], "gasLimit": ["0x061a80"], "gasPrice": "0x0a", "nonce": "0x00",
+    ///       "sender": "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b",
+    ///       "to": "0x0000000000000000000000000000000000001000", "value": ["0x01"]
+    ///     },
+    ///     "post": {
+    ///       "Shanghai": [{
+    ///         "hash": "0x00",
+    ///         "indexes": { "data": 0, "gas": 0, "value": 0 },
+    ///         "logs": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+    ///         "state": {
+    ///           "0x0000000000000000000000000000000000001000": { "balance": "0x01", "storage": { "0x00": "0x01" } }
+    ///         }
+    ///       }]
+    ///     }
+    ///   }
+    /// }"#;
+    ///
+    /// let tests = parse_tests(json).unwrap();
+    /// let results = tests["sstore"].run("Shanghai");
+    /// assert_eq!(results.len(), 1);
+    /// assert!(results[0].is_ok(), "{:?}", results[0]);
+    ///
+    /// assert!(tests["sstore"].run("Prague")[0].is_err());
+    /// ```
+    pub fn run(&self, fork: &str) -> Vec<Result<(), String>> {
+        let hardfork = match fork.parse::<Hardfork>() {
+            Ok(hardfork) => hardfork,
+            Err(e) => return vec![Err(e)],
+        };
+        let Some(posts) = self.post.get(fork) else {
+            return vec![Err(format!("No post-state for fork {}", fork))];
+        };
+
+        posts.iter().map(|post| self.run_post(post, hardfork)).collect()
+    }
+
+    fn run_post(&self, post: &PostState, hardfork: Hardfork) -> Result<(), String> {
+        let mut config = self.config(&post.indexes)?;
+        config.hardfork = hardfork;
+        let world = config.test_state.clone().ok_or("Missing world state")?;
+
+        let (sender, coinbase, gas_price) = (config.transaction.from, config.coinbase, config.transaction.gas_price);
+        let tip = if config.is_eip_enabled(1559, Hardfork::London) {
+            gas_price.saturating_sub(config.block_base_fee)
+        } else {
+            gas_price
+        };
+
+        let code = world
+            .borrow()
+            .accounts
+            .get(&TestState::account_key(&config.transaction.to))
+            .and_then(|account| account.code.as_ref())
+            .map(|code| parse_bytes(&code.bin))
+            .transpose()?
+            .unwrap_or_default();
+
+        let result = crate::vm::Evm::new(config).execute_transaction(code);
+
+        {
+            let gas = Word::from(result.gas_used.saturating_sub(result.gas_refund));
+            let mut world = world.borrow_mut();
+            let balance = world.balance(&sender).saturating_sub(gas.saturating_mul(gas_price));
+            world.set_balance(&sender, balance);
+            if !tip.is_zero() {
+                let balance = world.balance(&coinbase).saturating_add(gas.saturating_mul(tip));
+                world.set_balance(&coinbase, balance);
+            }
+        }
+
+        let expected_logs = parse_word(&post.logs)?;
+        let actual_logs = logs_hash(&result.logs);
+        if expected_logs != actual_logs {
+            return Err(format!("Logs hash mismatch: expected {:#x}, got {:#x}", expected_logs, actual_logs));
+        }
+
+        let world = world.borrow();
+        for (address, expected) in &post.state {
+            let address = parse_address(address)?;
+            let account = world.accounts.get(&TestState::account_key(&address));
+
+            if let Some(ref balance) = expected.balance {
+                let actual = account
+                    .and_then(|account| account.balance.as_deref())
+                    .map(parse_word)
+                    .transpose()?
+                    .unwrap_or_default();
+                if parse_word(balance)? != actual {
+                    return Err(format!("Balance mismatch for 0x{}: expected {}, got {:#x}", hex::encode(address), balance, actual));
+                }
+            }
+
+            let storage = world.storage(&address);
+            for (key, value) in &expected.storage {
+                let key = parse_word(key)?;
+                let actual = storage.get(&key).copied().unwrap_or_default();
+                if parse_word(value)? != actual {
+                    return Err(format!("Storage mismatch for 0x{} at {:#x}: expected {}, got {:#x}", hex::encode(address), key, value, actual));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use crate::state::EvmState;
+use crate::types::{Gas, Word};
+use serde::{Deserialize, Serialize};
+
+/// A single EIP-3155 trace line, recorded before the opcode executes
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceStep {
+    pub pc: usize,
+    pub op: u8,
+    #[serde(serialize_with = "hex_gas")]
+    pub gas: Gas,
+    /// Gas the opcode consumed, including dynamic costs charged while it ran
+    /// (memory expansion, SSTORE, calls, ...)
+    #[serde(serialize_with = "hex_gas")]
+    pub gas_cost: Gas,
+    /// Stack words, top of the stack last
+    #[serde(serialize_with = "hex_words")]
+    pub stack: Vec<Word>,
+    pub depth: usize,
+    pub mem_size: usize,
+}
+
+impl TraceStep {
+    /// Capture the state before the next opcode runs; `gas_cost` is filled in by `finish`
+    pub(crate) fn capture(state: &EvmState) -> Self {
+        Self {
+            pc: state.program_counter,
+            op: state.code[state.program_counter],
+            gas: state.gas_tracker.remaining(),
+            gas_cost: 0,
+            stack: state.stack.data().to_vec(),
+            depth: 1,
+            mem_size: state.memory.size(),
+        }
+    }
+
+    /// Record the gas the opcode consumed, from the gas left once it has run
+    pub(crate) fn finish(&mut self, state: &EvmState) {
+        self.gas_cost = self.gas - state.gas_tracker.remaining();
+    }
+
+    /// Serialize as one line of EIP-3155 JSON-lines output
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// One entry of a geth `structLogs` trace (`debug_traceTransaction`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLog {
+    pub pc: usize,
+    pub op: String,
+    pub gas: Gas,
+    /// Stack words as hex strings, top of the stack last
+    #[serde(default)]
+    pub stack: Vec<String>,
+}
+
+/// Parse a geth struct log, either the full `{"structLogs": [...]}` result or the bare array
+pub fn parse_struct_log(json: &str) -> Result<Vec<StructLog>, String> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Wrapped {
+        struct_logs: Vec<StructLog>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Input {
+        Wrapped(Wrapped),
+        Bare(Vec<StructLog>),
+    }
+
+    match serde_json::from_str(json).map_err(|e| e.to_string())? {
+        Input::Wrapped(wrapped) => Ok(wrapped.struct_logs),
+        Input::Bare(logs) => Ok(logs),
+    }
+}
+
+/// First point where an execution differs from a reference trace
+#[derive(Debug, Clone, PartialEq)]
+pub enum Divergence {
+    /// The reference trace could not be parsed
+    InvalidTrace(String),
+    /// A field of step `step` differs
+    Step {
+        step: usize,
+        field: &'static str,
+        expected: String,
+        actual: String,
+    },
+    /// One execution ran more steps than the other
+    Length { expected: usize, actual: usize },
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Divergence::InvalidTrace(error) => write!(f, "Invalid trace: {}", error),
+            Divergence::Step { step, field, expected, actual } => {
+                write!(f, "Step {}: {} expected {}, got {}", step, field, expected, actual)
+            }
+            Divergence::Length { expected, actual } => write!(f, "Expected {} steps, got {}", expected, actual),
+        }
+    }
+}
+
+/// Byte value of a geth opcode name, accepting geth's spellings of renamed opcodes
+fn struct_log_op(name: &str) -> Option<u8> {
+    match name {
+        "KECCAK256" => Some(0x20),
+        "PREVRANDAO" | "RANDOM" => Some(0x44),
+        _ => crate::assembler::opcode_byte(name),
+    }
+}
+
+/// Compare a recorded trace against a reference struct log, returning the first divergence
+pub(crate) fn compare(trace: &[TraceStep], reference: &[StructLog]) -> Result<(), Divergence> {
+    for (step, (actual, expected)) in trace.iter().zip(reference).enumerate() {
+        let mismatch = |field, expected: String, actual: String| Divergence::Step { step, field, expected, actual };
+
+        if actual.pc != expected.pc {
+            return Err(mismatch("pc", expected.pc.to_string(), actual.pc.to_string()));
+        }
+        if struct_log_op(&expected.op) != Some(actual.op) {
+            return Err(mismatch("op", expected.op.clone(), format!("{:#04x}", actual.op)));
+        }
+        if actual.gas != expected.gas {
+            return Err(mismatch("gas", expected.gas.to_string(), actual.gas.to_string()));
+        }
+        let expected_stack = expected
+            .stack
+            .iter()
+            .map(|word| crate::types::word_from_hex(word))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Divergence::InvalidTrace)?;
+        if actual.stack != expected_stack {
+            let format = |stack: &[Word]| format!("{:x?}", stack);
+            return Err(mismatch("stack", format(&expected_stack), format(&actual.stack)));
+        }
+    }
+
+    if trace.len() != reference.len() {
+        return Err(Divergence::Length { expected: reference.len(), actual: trace.len() });
+    }
+    Ok(())
+}
+
+fn hex_gas<S: serde::Serializer>(value: &Gas, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{:#x}", value))
+}
+
+fn hex_words<S: serde::Serializer>(values: &[Word], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(|value| format!("{:#x}", value)))
+}
@@ -7,6 +7,91 @@ pub type Address = [u8; 20];
 pub type Word = U256;
 pub type Gas = u64;
 
+/// Number of executions and total gas spent, per opcode
+pub type GasProfile = std::collections::HashMap<crate::opcodes::Opcode, (u64, Gas)>;
+
+/// Left-pad an address to a word, as it appears on the stack
+///
+/// # Example
+/// ```
+/// use evm::types::{address_to_word, word_to_address};
+/// use evm::Word;
+///
+/// assert_eq!(address_to_word([0u8; 20]), Word::zero());
+/// assert_eq!(address_to_word([0xff; 20]), (Word::one() << 160) - 1);
+///
+/// let mut high = [0u8; 20];
+/// high[0] = 0x80;
+/// assert_eq!(address_to_word(high), Word::one() << 159);
+///
+/// for address in [[0u8; 20], [0xff; 20], high, [0x12; 20]] {
+///     assert_eq!(word_to_address(address_to_word(address)), address);
+/// }
+/// ```
+pub fn address_to_word(address: Address) -> Word {
+    Word::from_big_endian(&address)
+}
+
+/// Take the low 20 bytes of a word as an address, ignoring the upper 12
+///
+/// # Example
+/// ```
+/// use evm::types::word_to_address;
+/// use evm::Word;
+///
+/// let mut expected = [0u8; 20];
+/// expected[19] = 0x42;
+/// assert_eq!(word_to_address(Word::from(0x42)), expected);
+/// assert_eq!(word_to_address(Word::MAX), [0xff; 20]);
+/// assert_eq!(word_to_address(Word::one() << 160), [0u8; 20]);
+/// ```
+pub fn word_to_address(word: Word) -> Address {
+    let mut bytes = [0u8; 32];
+    word.to_big_endian(&mut bytes);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&bytes[12..]);
+    address
+}
+
+/// Parse a hex number, with or without `0x`; an empty string is zero
+///
+/// # Example
+/// ```
+/// use evm::types::word_from_hex;
+/// use evm::Word;
+///
+/// assert_eq!(word_from_hex("0x0").unwrap(), Word::zero());
+/// assert_eq!(word_from_hex("0x").unwrap(), Word::zero());
+/// assert_eq!(word_from_hex("2a").unwrap(), Word::from(42));
+/// assert_eq!(word_from_hex("0x2A").unwrap(), Word::from(42));
+/// assert_eq!(word_from_hex(&format!("0x{}", "f".repeat(64))).unwrap(), Word::MAX);
+/// assert!(word_from_hex(&format!("0x1{}", "0".repeat(64))).is_err());
+/// assert!(word_from_hex("0xzz").is_err());
+/// ```
+pub fn word_from_hex(value: &str) -> Result<Word, String> {
+    let clean = value.strip_prefix("0x").unwrap_or(value);
+    if clean.is_empty() {
+        return Ok(Word::zero());
+    }
+    Word::from_str_radix(clean, 16).map_err(|e| e.to_string())
+}
+
+/// Format a word as minimal `0x`-prefixed lowercase hex
+///
+/// # Example
+/// ```
+/// use evm::types::{word_from_hex, word_to_hex};
+/// use evm::Word;
+///
+/// assert_eq!(word_to_hex(Word::zero()), "0x0");
+/// assert_eq!(word_to_hex(Word::from(42)), "0x2a");
+/// assert_eq!(word_to_hex(Word::MAX), format!("0x{}", "f".repeat(64)));
+/// assert_eq!(word_from_hex(&word_to_hex(Word::MAX)).unwrap(), Word::MAX);
+/// ```
+pub fn word_to_hex(word: Word) -> String {
+    format!("{:#x}", word)
+}
+
 /// Transaction data
 #[derive(Debug, Clone)]
 pub struct Transaction {
@@ -18,58 +103,413 @@ pub struct Transaction {
 }
 
 /// Account state for test configuration
-#[derive(Debug, Clone, serde::Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct AccountState {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub balance: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub code: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<std::collections::HashMap<String, String>>,
 }
 
 /// Code for test configuration
-#[derive(Debug, Clone, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Code {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub asm: Option<String>,
     pub bin: String,
 }
 
 /// Test state configuration
-#[derive(Debug, Clone, serde::Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct TestState {
     #[serde(flatten)]
     pub accounts: std::collections::HashMap<String, AccountState>,
 }
 
+/// The world state: every account keyed by its `0x`-prefixed address
+pub type WorldState = TestState;
+
+impl TestState {
+    /// Serialize to a JSON map of address -> {balance, nonce, code, storage}
+    ///
+    /// Keys are sorted so the output is stable across runs.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::types::WorldState;
+    ///
+    /// let json = r#"{
+    ///     "0x1000000000000000000000000000000000000aaa": {
+    ///         "balance": "0x10",
+    ///         "nonce": "0x1",
+    ///         "code": { "bin": "6001600055" },
+    ///         "storage": { "0x0": "0x1" }
+    ///     },
+    ///     "0x1e79b045dc29eae9fdc69673c9dcd7c53e5e159d": { "balance": "0xde0b6b3a7640000" }
+    /// }"#;
+    ///
+    /// let world = WorldState::from_json(json).unwrap();
+    /// assert_eq!(world.accounts.len(), 2);
+    ///
+    /// let round_trip = WorldState::from_json(&world.to_json()).unwrap();
+    /// assert_eq!(round_trip, world);
+    /// ```
+    pub fn to_json(&self) -> String {
+        serde_json::to_value(self)
+            .map(|value| value.to_string())
+            .unwrap_or_default()
+    }
+
+    /// Parse a world state previously written by `to_json` (or an `evm.json` test state)
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+
+    /// Key used for an address in the accounts map
+    pub fn account_key(address: &Address) -> String {
+        format!("0x{}", hex::encode(address))
+    }
+
+    /// Get the storage of an account, empty if the account has none
+    pub fn storage(&self, address: &Address) -> std::collections::HashMap<Word, Word> {
+        let mut storage = std::collections::HashMap::new();
+        if let Some(slots) = self.accounts.get(&Self::account_key(address)).and_then(|account| account.storage.as_ref()) {
+            for (key, value) in slots {
+                let key = word_from_hex(key).unwrap_or_default();
+                let value = word_from_hex(value).unwrap_or_default();
+                storage.insert(key, value);
+            }
+        }
+        storage
+    }
+
+    /// Get the balance of an account, zero if it does not exist
+    pub fn balance(&self, address: &Address) -> Word {
+        self.accounts
+            .get(&Self::account_key(address))
+            .and_then(|account| account.balance.as_ref())
+            .map(|balance| word_from_hex(balance).unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    /// Set the balance of an account, creating the account if needed
+    pub fn set_balance(&mut self, address: &Address, balance: Word) {
+        self.accounts.entry(Self::account_key(address)).or_default().balance = Some(format!("0x{:x}", balance));
+    }
+
+    /// Get the code of an account, empty if it has none
+    pub fn code(&self, address: &Address) -> Vec<u8> {
+        self.accounts
+            .get(&Self::account_key(address))
+            .and_then(|account| account.code.as_ref())
+            .map(|code| crate::util::parse_hex(&code.bin).unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    /// Whether an account exists, even if it is empty
+    ///
+    /// # Example
+    /// ```
+    /// use evm::types::TestState;
+    /// use evm::Word;
+    ///
+    /// let mut world = TestState::default();
+    /// assert!(!world.exists(&[0x01; 20]));
+    /// world.set_balance(&[0x01; 20], Word::zero());
+    /// assert!(world.exists(&[0x01; 20]));
+    /// assert!(world.is_empty(&[0x01; 20]));
+    /// ```
+    pub fn exists(&self, address: &Address) -> bool {
+        self.accounts.contains_key(&Self::account_key(address))
+    }
+
+    /// Whether an account has no balance, nonce or code (EIP-161), as one that does not exist
+    ///
+    /// # Example
+    /// ```
+    /// use evm::types::TestState;
+    /// use evm::Word;
+    ///
+    /// let mut world = TestState::default();
+    /// assert!(world.is_empty(&[0x01; 20]));
+    /// world.set_balance(&[0x01; 20], Word::zero());
+    /// assert!(world.is_empty(&[0x01; 20]));
+    /// world.set_balance(&[0x01; 20], Word::one());
+    /// assert!(!world.is_empty(&[0x01; 20]));
+    /// world.set_code(&[0x02; 20], &[0x00]);
+    /// assert!(!world.is_empty(&[0x02; 20]));
+    /// world.increment_nonce(&[0x03; 20]);
+    /// assert!(!world.is_empty(&[0x03; 20]));
+    /// ```
+    pub fn is_empty(&self, address: &Address) -> bool {
+        self.balance(address).is_zero() && self.nonce(address) == 0 && self.code(address).is_empty()
+    }
+
+    /// Get the nonce of an account, zero if it does not exist
+    pub fn nonce(&self, address: &Address) -> u64 {
+        self.accounts
+            .get(&Self::account_key(address))
+            .and_then(|account| account.nonce.as_ref())
+            .map(|nonce| u64::from_str_radix(nonce.trim_start_matches("0x"), 16).unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    /// Increment the nonce of an account, creating the account if needed
+    ///
+    /// Returns the nonce before the increment, which is the one a CREATE uses.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{Evm, EvmConfig, Word};
+    /// use evm::util::create_address;
+    ///
+    /// let config = EvmConfig::default();
+    /// let world = config.test_state.clone().unwrap();
+    /// let sender = config.transaction.to;
+    ///
+    /// // CREATE an empty contract twice from the same sender
+    /// let create = [0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0];
+    /// let result = Evm::new(config).execute([create, create].concat());
+    ///
+    /// let address = |nonce| Word::from_big_endian(&create_address(&sender, nonce));
+    /// assert_eq!(result.stack, vec![address(1), address(0)]);
+    /// assert_ne!(result.stack[0], result.stack[1]);
+    /// assert_eq!(world.borrow().nonce(&sender), 2);
+    /// ```
+    pub fn increment_nonce(&mut self, address: &Address) -> u64 {
+        let nonce = self.nonce(address);
+        self.accounts.entry(Self::account_key(address)).or_default().nonce = Some(format!("0x{:x}", nonce + 1));
+        nonce
+    }
+
+    /// Move `value` from one account to another
+    ///
+    /// Returns `false` without changing anything if the sender cannot afford it.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{Evm, EvmConfig, Word};
+    /// use evm::types::WorldState;
+    ///
+    /// let contract = [0xaa; 20];
+    /// let stops = [0xbb; 20];
+    /// let reverts = [0xcc; 20];
+    /// let world = WorldState::from_json(r#"{
+    ///     "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa": { "balance": "0x64" },
+    ///     "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb": { "code": { "bin": "00" } },
+    ///     "0xcccccccccccccccccccccccccccccccccccccccc": { "code": { "bin": "60006000fd" } }
+    /// }"#).unwrap();
+    ///
+    /// let mut config = EvmConfig::default();
+    /// config.transaction.to = contract;
+    /// config.test_state = Some(std::rc::Rc::new(std::cell::RefCell::new(world)));
+    /// let world = config.test_state.clone().unwrap();
+    ///
+    /// // CALL the given address with value 30 and no call or return data
+    /// let call = |callee: [u8; 20]| {
+    ///     let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x1e, 0x73];
+    ///     code.extend_from_slice(&callee);
+    ///     code.extend_from_slice(&[0x5a, 0xf1]);
+    ///     Evm::new(config.clone()).execute(code).stack
+    /// };
+    ///
+    /// // A successful call moves the value
+    /// assert_eq!(call(stops), vec![Word::one()]);
+    /// assert_eq!(world.borrow().balance(&contract), Word::from(70));
+    /// assert_eq!(world.borrow().balance(&stops), Word::from(30));
+    ///
+    /// // A reverting call gives it back
+    /// assert_eq!(call(reverts), vec![Word::zero()]);
+    /// assert_eq!(world.borrow().balance(&contract), Word::from(70));
+    /// assert_eq!(world.borrow().balance(&reverts), Word::zero());
+    ///
+    /// // An unaffordable call fails without running the callee
+    /// assert_eq!(call(stops), vec![Word::one()]);
+    /// assert_eq!(call(stops), vec![Word::one()]);
+    /// assert_eq!(call(stops), vec![Word::zero()]);
+    /// assert_eq!(world.borrow().balance(&contract), Word::from(10));
+    /// assert_eq!(world.borrow().balance(&stops), Word::from(90));
+    /// ```
+    pub fn transfer(&mut self, from: &Address, to: &Address, value: Word) -> bool {
+        if value.is_zero() {
+            return true;
+        }
+        let from_balance = self.balance(from);
+        if from_balance < value {
+            return false;
+        }
+        self.set_balance(from, from_balance - value);
+        let to_balance = self.balance(to);
+        self.set_balance(to, to_balance.overflowing_add(value).0);
+        true
+    }
+
+    /// Replace the code of an account, creating the account if needed
+    pub fn set_code(&mut self, address: &Address, code: &[u8]) {
+        self.accounts.entry(Self::account_key(address)).or_default().code = Some(Code {
+            asm: None,
+            bin: hex::encode(code),
+        });
+    }
+
+    /// Replace the storage of an account, creating the account if needed
+    pub fn set_storage(&mut self, address: &Address, storage: &std::collections::HashMap<Word, Word>) {
+        let slots: std::collections::HashMap<String, String> = storage
+            .iter()
+            .filter(|(_, value)| !value.is_zero())
+            .map(|(key, value)| (format!("0x{:x}", key), format!("0x{:x}", value)))
+            .collect();
+
+        let key = Self::account_key(address);
+        if slots.is_empty() && !self.accounts.contains_key(&key) {
+            return;
+        }
+        self.accounts.entry(key).or_default().storage = Some(slots);
+    }
+}
+
+/// Ethereum hardforks, in activation order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Hardfork {
+    Frontier,
+    Homestead,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    Paris,
+    Shanghai,
+    #[default]
+    Cancun,
+}
+
+/// Parse a fork name as state tests spell it
+///
+/// # Example
+/// ```
+/// use evm::Hardfork;
+///
+/// assert_eq!("Shanghai".parse(), Ok(Hardfork::Shanghai));
+/// assert_eq!("ConstantinopleFix".parse(), Ok(Hardfork::Petersburg));
+/// assert_eq!("Merge".parse(), Ok(Hardfork::Paris));
+/// assert!("Prague".parse::<Hardfork>().is_err());
+/// ```
+impl std::str::FromStr for Hardfork {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "Frontier" => Ok(Hardfork::Frontier),
+            "Homestead" => Ok(Hardfork::Homestead),
+            "Byzantium" => Ok(Hardfork::Byzantium),
+            "Constantinople" => Ok(Hardfork::Constantinople),
+            "ConstantinopleFix" | "Petersburg" => Ok(Hardfork::Petersburg),
+            "Istanbul" => Ok(Hardfork::Istanbul),
+            "Berlin" => Ok(Hardfork::Berlin),
+            "London" => Ok(Hardfork::London),
+            "Merge" | "Paris" => Ok(Hardfork::Paris),
+            "Shanghai" => Ok(Hardfork::Shanghai),
+            "Cancun" => Ok(Hardfork::Cancun),
+            _ => Err(format!("Unknown fork {}", name)),
+        }
+    }
+}
+
 /// EVM configuration
 #[derive(Debug, Clone)]
 pub struct EvmConfig {
     pub gas_limit: Gas,
+    pub initial_gas_refund: Gas,
+    /// Hard cap on memory size; growing beyond it fails with `MemoryOutOfBounds`
+    pub max_memory_bytes: usize,
+    /// Collect a `GasProfile` while executing
+    pub gas_profiling: bool,
+    /// Abort with `StepLimitExceeded` after this many opcodes, `None` for no limit
+    pub max_steps: Option<u64>,
+    /// Reject transactions with more calldata than this, `None` for no limit
+    pub max_calldata_bytes: Option<usize>,
+    /// Run in a static frame, as inside STATICCALL, where state modifications fail
+    pub static_context: bool,
     pub block_number: u64,
     pub block_timestamp: u64,
     pub block_difficulty: U256,
+    pub block_prevrandao: Word,
+    /// Hashes of recent blocks by number, served by BLOCKHASH for the last 256 blocks
+    pub block_hashes: std::collections::HashMap<u64, Word>,
     pub block_gas_limit: U256,
     pub block_base_fee: U256,
+    /// Excess blob gas of the block, from which the blob base fee is derived
+    pub excess_blob_gas: u64,
+    /// Blob base fee returned by BLOBBASEFEE
+    pub block_blob_base_fee: Word,
     pub chain_id: U256,
     pub coinbase: Address,
+    /// Externally-owned account that sent the transaction, returned by ORIGIN
+    pub origin: Address,
+    pub hardfork: Hardfork,
+    /// EIPs enabled on top of the hardfork, e.g. 3855 for PUSH0 before Shanghai
+    pub enabled_eips: std::collections::HashSet<u32>,
     pub transaction: Transaction,
+    /// Position of the transaction in its block, recorded on every log it emits
+    pub transaction_index: u64,
+    /// Versioned hashes of the transaction's blobs, read by BLOBHASH
+    pub blob_versioned_hashes: Vec<Word>,
+    /// Addresses and storage slots that start warm (EIP-2930)
+    pub access_list: Vec<(Address, Vec<Word>)>,
     pub test_state: Option<Rc<RefCell<TestState>>>,
 }
 
+/// Default configuration
+///
+/// The transaction `to` and `from` addresses are the zero address; callers
+/// running contract code that depends on them should set them explicitly.
+///
+/// # Example
+/// ```
+/// use evm::EvmConfig;
+///
+/// assert_eq!(EvmConfig::default().transaction.to, [0u8; 20]);
+/// assert_eq!(EvmConfig::default().transaction.from, [0u8; 20]);
+/// ```
 impl Default for EvmConfig {
     fn default() -> Self {
         Self {
             gas_limit: 30_000_000,
+            initial_gas_refund: 0,
+            max_memory_bytes: crate::memory::DEFAULT_MAX_MEMORY_BYTES,
+            gas_profiling: false,
+            max_steps: None,
+            max_calldata_bytes: None,
+            static_context: false,
             block_number: 0,
             block_timestamp: 0,
             block_difficulty: U256::zero(),
+            block_prevrandao: Word::zero(),
+            block_hashes: std::collections::HashMap::new(),
             block_gas_limit: U256::from(30_000_000),
             block_base_fee: U256::from(1),
+            excess_blob_gas: 0,
+            block_blob_base_fee: U256::from(crate::gas::MIN_BLOB_BASE_FEE),
             chain_id: U256::from(1),
             coinbase: [0u8; 20],
+            origin: [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x13, 0x37],
+            hardfork: Hardfork::default(),
+            enabled_eips: std::collections::HashSet::new(),
+            transaction_index: 0,
+            blob_versioned_hashes: Vec::new(),
+            access_list: Vec::new(),
             test_state: Some(Rc::new(RefCell::new(TestState {
                 accounts: std::collections::HashMap::new(),
             }))),
             transaction: Transaction {
-                to: [0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0A, 0xAA],
-                from: [0x1E, 0x79, 0xB0, 0x45, 0xDC, 0x29, 0xEA, 0xE9, 0xFD, 0xC6, 0x96, 0x73, 0xC9, 0xDC, 0xD7, 0xC5, 0x3E, 0x5E, 0x15, 0x9D],
+                to: [0u8; 20],
+                from: [0u8; 20],
                 value: U256::zero(),
                 gas_price: U256::from(0x99),
                 data: Vec::new(),
@@ -78,22 +518,330 @@ impl Default for EvmConfig {
     }
 }
 
+impl EvmConfig {
+    /// Whether an EIP applies: either its hardfork is active or it was enabled explicitly
+    pub fn is_eip_enabled(&self, eip: u32, hardfork: Hardfork) -> bool {
+        self.hardfork >= hardfork || self.enabled_eips.contains(&eip)
+    }
+
+    /// Whether an opcode is available under the configured hardfork and EIPs
+    pub fn is_opcode_enabled(&self, opcode: crate::opcodes::Opcode) -> bool {
+        match opcode.eip() {
+            Some(eip) => self.is_eip_enabled(eip, opcode.introduced_in()),
+            None => self.hardfork >= opcode.introduced_in(),
+        }
+    }
+
+    /// Check the invariants execution relies on
+    ///
+    /// The gas limit must be positive, the block gas limit must fit in a `Gas`
+    /// and every account in the test state must be keyed by a 20-byte address.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmConfig, Word};
+    ///
+    /// assert_eq!(EvmConfig::default().validate(), Ok(()));
+    ///
+    /// let mut config = EvmConfig::default();
+    /// config.block_gas_limit = Word::from(u64::MAX) + 1;
+    /// assert!(config.validate().is_err());
+    ///
+    /// let mut config = EvmConfig::default();
+    /// config.gas_limit = 0;
+    /// assert!(config.validate().is_err());
+    ///
+    /// let config = EvmConfig::default();
+    /// config.test_state.as_ref().unwrap().borrow_mut().accounts.insert("0xbeef".to_string(), Default::default());
+    /// assert!(config.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), String> {
+        if self.gas_limit == 0 {
+            return Err("Gas limit must be positive".to_string());
+        }
+        if self.block_gas_limit > U256::from(Gas::MAX) {
+            return Err(format!("Block gas limit {:#x} does not fit in 64 bits", self.block_gas_limit));
+        }
+        if let Some(ref world) = self.test_state {
+            for key in world.borrow().accounts.keys() {
+                if !crate::util::parse_hex(key).is_ok_and(|bytes| bytes.len() == 20) {
+                    return Err(format!("Invalid account address {}", key));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Blob base fee derived from the excess blob gas (EIP-4844)
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmConfig, Word};
+    ///
+    /// let mut config = EvmConfig::default();
+    /// assert_eq!(config.blob_base_fee(), Word::from(1));
+    ///
+    /// // Ten update fractions of excess gas multiply the fee by about e^10
+    /// config.excess_blob_gas = 10 * 3338477;
+    /// assert_eq!(config.blob_base_fee(), Word::from(22026));
+    /// ```
+    pub fn blob_base_fee(&self) -> Word {
+        crate::gas::fake_exponential(
+            crate::gas::MIN_BLOB_BASE_FEE,
+            self.excess_blob_gas,
+            crate::gas::BLOB_BASE_FEE_UPDATE_FRACTION,
+        )
+    }
+
+    /// Configuration with the fixed addresses expected by the `evm.json` test suite
+    pub fn test_default() -> Self {
+        let mut config = Self::default();
+        config.transaction.to = [0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0A, 0xAA];
+        config.transaction.from = [0x1E, 0x79, 0xB0, 0x45, 0xDC, 0x29, 0xEA, 0xE9, 0xFD, 0xC6, 0x96, 0x73, 0xC9, 0xDC, 0xD7, 0xC5, 0x3E, 0x5E, 0x15, 0x9D];
+        config
+    }
+}
+
 /// EVM execution result
-#[derive(Debug, Clone)]
+///
+/// Serializes to JSON with words, addresses and byte strings as `0x`-prefixed hex.
+///
+/// # Example
+/// ```
+/// use evm::evm;
+///
+/// // MSTORE 0xbb at 0, then LOG1 one byte with topic 0x11..11
+/// let code = hex::decode("60bb6000527f11111111111111111111111111111111111111111111111111111111111111116001601fa1").unwrap();
+/// let result = evm(code);
+///
+/// let json = serde_json::to_value(&result).unwrap();
+/// assert_eq!(json["success"], true);
+/// assert_eq!(json["stack"], serde_json::json!([]));
+/// assert_eq!(json["logs"][0]["address"], "0x0000000000000000000000000000000000000000");
+/// assert_eq!(json["logs"][0]["topics"][0], format!("0x{}", "11".repeat(32)));
+/// assert_eq!(json["logs"][0]["data"], "0xbb");
+/// ```
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct EvmResult {
     pub success: bool,
     pub gas_used: Gas,
+    /// Gas left over from the limit the execution started with
+    pub gas_remaining: Gas,
+    pub gas_refund: Gas,
+    /// How execution ended; `success` is true for `Stopped` and `Returned`
+    pub outcome: Outcome,
+    #[serde(serialize_with = "hex_serde::words")]
     pub stack: Vec<Word>,
+    /// Output of the final RETURN or REVERT, empty if execution ended any other way
+    #[serde(serialize_with = "hex_serde::bytes")]
     pub return_data: Vec<u8>,
     pub logs: Vec<Log>,
+    /// Contents of memory when execution ended
+    ///
+    /// # Example
+    /// ```
+    /// use evm::evm;
+    ///
+    /// // PUSH1 0xff PUSH1 0 MSTORE8
+    /// let result = evm(vec![0x60, 0xff, 0x60, 0x00, 0x53]);
+    /// let mut expected = vec![0u8; 32];
+    /// expected[0] = 0xff;
+    /// assert_eq!(result.final_memory, expected);
+    /// ```
+    #[serde(serialize_with = "hex_serde::bytes")]
+    pub final_memory: Vec<u8>,
+    /// Gas spent per opcode, only collected when gas profiling is enabled
+    #[serde(skip)]
+    pub gas_profile: Option<GasProfile>,
+    /// Address of the contract deployed by a successful creation transaction
+    #[serde(serialize_with = "hex_serde::optional_address")]
+    pub created_address: Option<Address>,
+    /// Code of the contract deployed by a successful creation transaction
+    #[serde(serialize_with = "hex_serde::optional_bytes")]
+    pub deployed_code: Option<Vec<u8>>,
+}
+
+/// How an execution ended
+///
+/// # Example
+/// ```
+/// use evm::{evm, EvmError};
+/// use evm::types::Outcome;
+///
+/// // STOP, or simply running off the end of the code
+/// assert_eq!(evm(vec![0x00]).outcome, Outcome::Stopped);
+/// assert_eq!(evm(vec![0x60, 0x01]).outcome, Outcome::Stopped);
+///
+/// // PUSH1 0 PUSH1 0 RETURN / REVERT
+/// assert_eq!(evm(vec![0x60, 0x00, 0x60, 0x00, 0xf3]).outcome, Outcome::Returned);
+/// let reverted = evm(vec![0x60, 0x00, 0x60, 0x00, 0xfd]);
+/// assert_eq!(reverted.outcome, Outcome::Reverted);
+/// assert!(!reverted.success);
+///
+/// // POP on an empty stack
+/// let halted = evm(vec![0x50]);
+/// assert_eq!(halted.outcome, Outcome::Halted(EvmError::StackUnderflow));
+/// assert!(!halted.success);
+/// ```
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub enum Outcome {
+    /// Ended with STOP, SELFDESTRUCT or by running past the end of the code
+    Stopped,
+    /// Ended with RETURN
+    Returned,
+    /// Ended with REVERT
+    Reverted,
+    /// Aborted by an error
+    Halted(EvmError),
+}
+
+impl EvmResult {
+    /// Interpret the return data as a single word
+    ///
+    /// Data shorter than 32 bytes is right-aligned; empty or longer data gives `None`.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{evm, Word};
+    ///
+    /// // PUSH1 0x2a PUSH1 0 MSTORE PUSH1 <size> PUSH1 <offset> RETURN
+    /// let returning = |size: u8, offset: u8| evm(vec![0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, size, 0x60, offset, 0xf3]);
+    ///
+    /// assert_eq!(returning(32, 0).return_word(), Some(Word::from(0x2a)));
+    /// assert_eq!(returning(2, 30).return_word(), Some(Word::from(0x2a)));
+    /// assert_eq!(returning(0, 0).return_word(), None);
+    /// assert_eq!(returning(33, 0).return_word(), None);
+    /// ```
+    pub fn return_word(&self) -> Option<Word> {
+        if self.return_data.is_empty() || self.return_data.len() > 32 {
+            return None;
+        }
+        Some(Word::from_big_endian(&self.return_data))
+    }
+
+    /// The return data as a 32-byte array, if it is exactly 32 bytes long
+    ///
+    /// # Example
+    /// ```
+    /// use evm::evm;
+    ///
+    /// // PUSH1 0x2a PUSH1 0 MSTORE PUSH1 <size> PUSH1 0 RETURN
+    /// let returning = |size: u8| evm(vec![0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, size, 0x60, 0x00, 0xf3]);
+    ///
+    /// let mut expected = [0u8; 32];
+    /// expected[31] = 0x2a;
+    /// assert_eq!(returning(32).return_bytes32(), Some(expected));
+    /// assert_eq!(returning(31).return_bytes32(), None);
+    /// assert_eq!(returning(0).return_bytes32(), None);
+    /// ```
+    pub fn return_bytes32(&self) -> Option<[u8; 32]> {
+        self.return_data.as_slice().try_into().ok()
+    }
+
+    /// Logs whose first topic, the event signature for Solidity events, is `topic`
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{evm, Word};
+    ///
+    /// // LOG1 with topic 0xaa, then LOG2 with topics 0xbb and 0xaa, both without data:
+    /// // PUSH1 0xaa PUSH1 0 PUSH1 0 LOG1 PUSH1 0xaa PUSH1 0xbb PUSH1 0 PUSH1 0 LOG2
+    /// let result = evm(vec![
+    ///     0x60, 0xaa, 0x60, 0x00, 0x60, 0x00, 0xa1,
+    ///     0x60, 0xaa, 0x60, 0xbb, 0x60, 0x00, 0x60, 0x00, 0xa2,
+    /// ]);
+    /// assert_eq!(result.logs.len(), 2);
+    ///
+    /// let aa = result.logs_with_topic(Word::from(0xaa));
+    /// assert_eq!(aa.len(), 1);
+    /// assert_eq!(aa[0].topics.len(), 1);
+    ///
+    /// let bb = result.logs_with_topic(Word::from(0xbb));
+    /// assert_eq!(bb.len(), 1);
+    /// assert_eq!(bb[0].topics, vec![Word::from(0xbb), Word::from(0xaa)]);
+    ///
+    /// assert!(result.logs_with_topic(Word::zero()).is_empty());
+    /// ```
+    pub fn logs_with_topic(&self, topic: Word) -> Vec<&Log> {
+        self.logs.iter().filter(|log| log.topic0() == Some(topic)).collect()
+    }
 }
 
 /// EVM log entry
-#[derive(Debug, Clone)]
+///
+/// # Example
+/// ```
+/// use evm::EvmBuilder;
+///
+/// // PUSH1 0 PUSH1 0 LOG0, three times
+/// let code = [0x60, 0x00, 0x60, 0x00, 0xa0].repeat(3);
+/// let mut evm = EvmBuilder::new().block_number(17).build();
+/// let mut config = evm.config().clone();
+/// config.transaction_index = 4;
+/// evm.update_config(config);
+///
+/// let result = evm.execute(code);
+/// let indexes: Vec<u64> = result.logs.iter().map(|log| log.log_index).collect();
+/// assert_eq!(indexes, vec![0, 1, 2]);
+/// assert!(result.logs.iter().all(|log| log.block_number == 17 && log.transaction_index == 4));
+/// ```
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Log {
+    #[serde(serialize_with = "hex_serde::address")]
     pub address: Address,
+    #[serde(serialize_with = "hex_serde::words")]
     pub topics: Vec<U256>,
+    #[serde(serialize_with = "hex_serde::bytes")]
     pub data: Vec<u8>,
+    /// Number of the block the log was emitted in
+    pub block_number: u64,
+    /// Position of the log among those the transaction emitted
+    pub log_index: u64,
+    /// Position of the emitting transaction in its block
+    pub transaction_index: u64,
+}
+
+impl Log {
+    /// The first topic, which identifies the event; `None` for LOG0
+    pub fn topic0(&self) -> Option<Word> {
+        self.topics.first().copied()
+    }
+}
+
+/// Serde helpers encoding EVM values as `0x`-prefixed hex strings
+mod hex_serde {
+    use super::{Address, Word};
+    use serde::ser::{SerializeSeq, Serializer};
+
+    pub fn words<S: Serializer>(values: &[Word], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(values.len()))?;
+        for value in values {
+            seq.serialize_element(&format!("{:#x}", value))?;
+        }
+        seq.end()
+    }
+
+    pub fn address<S: Serializer>(value: &Address, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(value)))
+    }
+
+    pub fn bytes<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(value)))
+    }
+
+    pub fn optional_address<S: Serializer>(value: &Option<Address>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => address(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn optional_bytes<S: Serializer>(value: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => bytes(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
 }
 
 /// Block information for test configuration
@@ -115,17 +863,32 @@ pub struct TestTransaction {
     pub data: Option<String>,
     pub to: Option<String>,
     pub from: Option<String>,
+    pub origin: Option<String>,
+    /// Gas limit of the transaction
+    pub gas: Option<String>,
 }
 
 /// EVM execution error
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
 pub enum EvmError {
     OutOfGas,
     InvalidOpcode(u8),
+    /// The designated INVALID instruction (0xfe) was executed
+    Invalid,
     StackUnderflow,
     StackOverflow,
     MemoryOutOfBounds,
     InvalidJumpDestination,
     ExecutionReverted,
+    /// More opcodes ran than `EvmConfig::max_steps` allows
+    StepLimitExceeded,
+    /// The transaction carries more calldata than `EvmConfig::max_calldata_bytes` allows
+    CalldataTooLarge,
+    /// A creation returned more code than `MAX_CODE_SIZE` (EIP-170)
+    CodeSizeExceeded,
+    /// A creation's init code is longer than `MAX_INITCODE_SIZE` (EIP-3860)
+    InitcodeSizeExceeded,
+    /// The sender cannot afford the value of the transaction
+    InsufficientBalance,
     Unknown(String),
 }
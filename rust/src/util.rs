@@ -0,0 +1,208 @@
+use crate::types::{Address, Word};
+use std::cmp::Ordering;
+
+/// Whether a word is negative when read as a two's-complement signed integer
+///
+/// # Example
+/// ```
+/// use evm::{util::is_negative, Word};
+///
+/// assert!(!is_negative(Word::zero()));
+/// assert!(!is_negative(Word::max_value() >> 1));
+/// assert!(is_negative(Word::one() << 255));
+/// assert!(is_negative(Word::max_value()));
+/// ```
+pub fn is_negative(w: Word) -> bool {
+    w.bit(255)
+}
+
+/// Two's-complement negation of a word
+///
+/// Wraps instead of overflowing, so it is defined on the full range:
+/// zero stays zero and -2^255 (which has no positive counterpart) maps to itself.
+///
+/// # Example
+/// ```
+/// use evm::{util::negate, Word};
+///
+/// let min = Word::one() << 255;
+/// assert_eq!(negate(Word::zero()), Word::zero());
+/// assert_eq!(negate(Word::one()), Word::max_value());
+/// assert_eq!(negate(Word::max_value()), Word::one());
+/// assert_eq!(negate(min), min);
+/// assert_eq!(negate(negate(Word::from(42))), Word::from(42));
+/// ```
+pub fn negate(w: Word) -> Word {
+    (!w).overflowing_add(Word::one()).0
+}
+
+/// Magnitude of a signed word; -2^255 has none that fits, and stays -2^255
+///
+/// Read as unsigned, that is still the right magnitude, which is all SDIV and SMOD need.
+///
+/// # Example
+/// ```
+/// use evm::{util::abs, Word};
+///
+/// let min = Word::one() << 255;
+/// assert_eq!(abs(Word::from(5)), Word::from(5));
+/// assert_eq!(abs(Word::max_value()), Word::one());
+/// assert_eq!(abs(min), min);
+/// ```
+pub fn abs(w: Word) -> Word {
+    if is_negative(w) { negate(w) } else { w }
+}
+
+/// Compare two words as two's-complement signed integers
+///
+/// # Example
+/// ```
+/// use std::cmp::Ordering;
+/// use evm::{util::signed_cmp, Word};
+///
+/// let min = Word::one() << 255;
+/// let minus_one = Word::max_value();
+/// assert_eq!(signed_cmp(Word::zero(), Word::zero()), Ordering::Equal);
+/// assert_eq!(signed_cmp(minus_one, Word::zero()), Ordering::Less);
+/// assert_eq!(signed_cmp(Word::one(), minus_one), Ordering::Greater);
+/// assert_eq!(signed_cmp(min, minus_one), Ordering::Less);
+/// assert_eq!(signed_cmp(min, Word::max_value() >> 1), Ordering::Less);
+/// assert_eq!(signed_cmp(minus_one - 1, minus_one), Ordering::Less);
+/// ```
+pub fn signed_cmp(a: Word, b: Word) -> Ordering {
+    match (is_negative(a), is_negative(b)) {
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        // Same sign: two's complement preserves the unsigned order
+        _ => a.cmp(&b),
+    }
+}
+
+/// Convert a word to a `usize`, `None` if it does not fit
+///
+/// # Example
+/// ```
+/// use evm::{util::word_to_usize, Word};
+///
+/// assert_eq!(word_to_usize(Word::from(42)), Some(42));
+/// assert_eq!(word_to_usize(Word::from(usize::MAX)), Some(usize::MAX));
+/// assert_eq!(word_to_usize(Word::from(usize::MAX) + 1), None);
+/// assert_eq!(word_to_usize(Word::one() << 255), None);
+/// ```
+pub fn word_to_usize(w: Word) -> Option<usize> {
+    if w > Word::from(usize::MAX) { None } else { Some(w.as_usize()) }
+}
+
+/// Decode a hex string, with or without `0x`, left-padding odd-length input with a zero
+///
+/// # Example
+/// ```
+/// use evm::util::parse_hex;
+///
+/// assert_eq!(parse_hex("0x1").unwrap(), vec![0x01]);
+/// assert_eq!(parse_hex("1").unwrap(), vec![0x01]);
+/// assert_eq!(parse_hex("0x01").unwrap(), vec![0x01]);
+/// assert_eq!(parse_hex("0x123").unwrap(), vec![0x01, 0x23]);
+/// assert_eq!(parse_hex("").unwrap(), Vec::<u8>::new());
+/// assert_eq!(parse_hex("0x").unwrap(), Vec::<u8>::new());
+/// assert!(parse_hex("0xzz").is_err());
+/// ```
+pub fn parse_hex(value: &str) -> Result<Vec<u8>, hex::FromHexError> {
+    let clean = value.strip_prefix("0x").unwrap_or(value);
+    if clean.len() % 2 == 1 {
+        hex::decode(format!("0{}", clean))
+    } else {
+        hex::decode(clean)
+    }
+}
+
+/// Address of a contract deployed with CREATE
+///
+/// `keccak256(rlp([sender, nonce]))[12..]`
+///
+/// # Example
+/// ```
+/// use evm::util::create_address;
+///
+/// let sender: [u8; 20] = hex::decode("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0").unwrap().try_into().unwrap();
+/// assert_eq!(hex::encode(create_address(&sender, 0)), "cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d");
+/// assert_eq!(hex::encode(create_address(&sender, 1)), "343c43a37d37dff08ae8c4a11544c718abb4fcf8");
+/// ```
+pub fn create_address(sender: &Address, nonce: u64) -> Address {
+    use sha3::{Digest, Keccak256};
+
+    // RLP of the nonce: a single byte below 0x80, otherwise a length-prefixed big-endian string
+    let nonce_bytes: Vec<u8> = nonce.to_be_bytes().iter().copied().skip_while(|&b| b == 0).collect();
+    let mut encoded_nonce = Vec::new();
+    if nonce_bytes.len() != 1 || nonce_bytes[0] >= 0x80 {
+        encoded_nonce.push(0x80 + nonce_bytes.len() as u8);
+    }
+    encoded_nonce.extend_from_slice(&nonce_bytes);
+
+    // The list payload is at most 21 + 9 bytes, so it always has a short prefix
+    let mut encoded = vec![0xc0 + (21 + encoded_nonce.len()) as u8, 0x80 + 20];
+    encoded.extend_from_slice(sender);
+    encoded.extend_from_slice(&encoded_nonce);
+
+    let hash = Keccak256::digest(&encoded);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+/// Address of a contract deployed with CREATE2 (EIP-1014)
+///
+/// `keccak256(0xff ++ sender ++ salt ++ keccak256(initcode))[12..]`
+///
+/// # Example
+/// ```
+/// use evm::{util::create2_address, Word};
+///
+/// // Example 0 from EIP-1014
+/// let address = create2_address(&[0u8; 20], Word::zero(), &[0x00]);
+/// assert_eq!(hex::encode(address), "4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38");
+///
+/// // An empty initcode hashes as the empty string
+/// let address = create2_address(&[0u8; 20], Word::zero(), &[]);
+/// assert_eq!(hex::encode(address), "e33c0c7f7df4809055c3eba6c09cfe4baf1bd9e0");
+/// ```
+pub fn create2_address(sender: &Address, salt: Word, initcode: &[u8]) -> Address {
+    use sha3::{Digest, Keccak256};
+
+    let mut salt_bytes = [0u8; 32];
+    salt.to_big_endian(&mut salt_bytes);
+
+    let mut hasher = Keccak256::new();
+    hasher.update([0xff]);
+    hasher.update(sender);
+    hasher.update(salt_bytes);
+    hasher.update(Keccak256::digest(initcode));
+    let hash = hasher.finalize();
+
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+/// Calldata for a function call: the 4-byte selector followed by the ABI
+/// encoding of `args`, each a static 32-byte word
+///
+/// # Example
+/// ```
+/// use evm::{util::encode_call, Word};
+///
+/// let calldata = encode_call([0xa9, 0x05, 0x9c, 0xbb], &[Word::from(0xbeef), Word::from(10)]);
+/// assert_eq!(calldata.len(), 4 + 2 * 32);
+/// assert_eq!(calldata[..4], [0xa9, 0x05, 0x9c, 0xbb]);
+/// assert_eq!(Word::from_big_endian(&calldata[4..36]), Word::from(0xbeef));
+/// assert_eq!(Word::from_big_endian(&calldata[36..]), Word::from(10));
+/// ```
+pub fn encode_call(selector: [u8; 4], args: &[Word]) -> Vec<u8> {
+    let mut calldata = selector.to_vec();
+    for arg in args {
+        let mut word = [0u8; 32];
+        arg.to_big_endian(&mut word);
+        calldata.extend_from_slice(&word);
+    }
+    calldata
+}
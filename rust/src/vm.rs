@@ -1,5 +1,6 @@
-use crate::types::{EvmError, EvmConfig, EvmResult, Address, Word};
-use crate::state::EvmState;
+use crate::types::{Address, EvmConfig, EvmError, EvmResult, Hardfork, Word};
+use crate::state::{EvmState, ExecutionStatus};
+use crate::trace::{Divergence, TraceStep};
 use crate::Gas;
 use primitive_types::U256;
 
@@ -14,19 +15,343 @@ impl Evm {
     }
 
     /// Execute EVM bytecode
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{Evm, Word};
+    ///
+    /// // PUSH1 2 PUSH1 3 ADD
+    /// let result = Evm::default().execute(vec![0x60, 0x02, 0x60, 0x03, 0x01]);
+    /// assert!(result.success);
+    /// assert_eq!(result.stack, vec![Word::from(5)]);
+    /// ```
     pub fn execute(&self, code: Vec<u8>) -> EvmResult {
+        self.execute_debug(code).0
+    }
+
+    /// Execute EVM bytecode as the target of `config.transaction`
+    ///
+    /// Intrinsic gas for the transaction and its calldata is charged before the
+    /// code runs; a gas limit below it fails with `OutOfGas` without running anything.
+    /// Likewise, calldata beyond `max_calldata_bytes` fails with `CalldataTooLarge`.
+    ///
+    /// The sender's nonce is bumped, and the value moves to the recipient before
+    /// the code runs and back if it fails; a sender that cannot afford the value
+    /// fails with `InsufficientBalance`.
+    ///
+    /// A zero `to` address makes this a contract creation: `code` is run as
+    /// init code, without calldata, at the address derived from the sender and
+    /// its nonce, and whatever it RETURNs is deployed there. Creation costs
+    /// `GAS_CREATE` more, and its calldata cost is charged on the init code.
+    /// Init code that reverts deploys nothing, and its revert data is returned.
+    /// Init code over `MAX_INITCODE_SIZE` bytes, or that returns over
+    /// `MAX_CODE_SIZE` bytes of code, fails the creation.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, EvmError};
+    /// use evm::types::Outcome;
+    ///
+    /// let mut evm = EvmBuilder::new().build();
+    /// let mut config = evm.config().clone();
+    /// config.transaction.to = [0x11; 20];
+    /// config.transaction.data = vec![0x00, 0x00, 0xff];
+    /// evm.update_config(config.clone());
+    ///
+    /// // PUSH1 1 POP
+    /// let result = evm.execute_transaction(vec![0x60, 0x01, 0x50]);
+    /// assert!(result.success);
+    /// assert_eq!(result.gas_used, 21000 + 2 * 4 + 16 + 3 + 2);
+    ///
+    /// config.gas_limit = 21000;
+    /// evm.update_config(config);
+    /// let result = evm.execute_transaction(vec![0x60, 0x01, 0x50]);
+    /// assert_eq!(result.outcome, Outcome::Halted(EvmError::OutOfGas));
+    /// assert_eq!(result.created_address, None);
+    ///
+    /// // Creation: PUSH4 0x6005600a PUSH1 0 MSTORE PUSH1 4 PUSH1 28 RETURN
+    /// // deploys the runtime code PUSH1 5 PUSH1 10
+    /// let init_code = vec![0x63, 0x60, 0x05, 0x60, 0x0a, 0x60, 0x00, 0x52, 0x60, 0x04, 0x60, 0x1c, 0xf3];
+    /// let evm = EvmBuilder::new().build();
+    /// let result = evm.execute_transaction(init_code);
+    /// assert!(result.success);
+    /// assert_eq!(result.deployed_code, Some(vec![0x60, 0x05, 0x60, 0x0a]));
+    ///
+    /// let address = result.created_address.unwrap();
+    /// assert_eq!(address, evm::util::create_address(&[0u8; 20], 0));
+    /// let world = evm.config().test_state.as_ref().unwrap().borrow();
+    /// assert_eq!(world.accounts[&evm::types::TestState::account_key(&address)].code.as_ref().unwrap().bin, "6005600a");
+    ///
+    /// // A constructor reverting with Error("nope"):
+    /// // PUSH4 0x08c379a0 PUSH1 0xe0 SHL PUSH1 0 MSTORE PUSH1 0x20 PUSH1 4 MSTORE
+    /// // PUSH1 4 PUSH1 0x24 MSTORE PUSH4 "nope" PUSH1 0xe0 SHL PUSH1 0x44 MSTORE
+    /// // PUSH1 100 PUSH1 0 REVERT
+    /// let init_code = vec![
+    ///     0x63, 0x08, 0xc3, 0x79, 0xa0, 0x60, 0xe0, 0x1b, 0x60, 0x00, 0x52,
+    ///     0x60, 0x20, 0x60, 0x04, 0x52,
+    ///     0x60, 0x04, 0x60, 0x24, 0x52,
+    ///     0x63, b'n', b'o', b'p', b'e', 0x60, 0xe0, 0x1b, 0x60, 0x44, 0x52,
+    ///     0x60, 0x64, 0x60, 0x00, 0xfd,
+    /// ];
+    /// let evm = EvmBuilder::new().build();
+    /// let result = evm.execute_transaction(init_code);
+    /// assert!(!result.success);
+    /// assert_eq!(result.created_address, None);
+    /// assert_eq!(result.deployed_code, None);
+    ///
+    /// let mut reason = evm::util::encode_call([0x08, 0xc3, 0x79, 0xa0], &[evm::Word::from(0x20), evm::Word::from(4)]);
+    /// reason.extend_from_slice(b"nope");
+    /// reason.resize(100, 0);
+    /// assert_eq!(result.return_data, reason);
+    ///
+    /// let address = evm::util::create_address(&[0u8; 20], 0);
+    /// assert!(evm.config().test_state.as_ref().unwrap().borrow().code(&address).is_empty());
+    ///
+    /// // PUSH2 size PUSH1 0 RETURN - deploys `size` zero bytes
+    /// let deploy = |size: u16| {
+    ///     let [high, low] = size.to_be_bytes();
+    ///     EvmBuilder::new().build().execute_transaction(vec![0x61, high, low, 0x60, 0x00, 0xf3])
+    /// };
+    /// assert_eq!(deploy(24576).deployed_code.map(|code| code.len()), Some(24576));
+    /// let result = deploy(24577);
+    /// assert_eq!(result.outcome, Outcome::Halted(EvmError::CodeSizeExceeded));
+    /// assert_eq!(result.gas_remaining, 0);
+    ///
+    /// // Sending 5 wei to 0x11..11 with 3 to spend, then with 8
+    /// let mut evm = EvmBuilder::new().build();
+    /// let mut config = evm.config().clone();
+    /// config.transaction.to = [0x11; 20];
+    /// config.transaction.value = evm::Word::from(5);
+    /// let world = config.test_state.clone().unwrap();
+    /// world.borrow_mut().set_balance(&config.transaction.from, evm::Word::from(3));
+    /// evm.update_config(config.clone());
+    /// let result = evm.execute_transaction(vec![]);
+    /// assert_eq!(result.outcome, Outcome::Halted(EvmError::InsufficientBalance));
+    /// assert_eq!(world.borrow().balance(&[0x11; 20]), evm::Word::zero());
+    ///
+    /// world.borrow_mut().set_balance(&config.transaction.from, evm::Word::from(8));
+    /// assert!(evm.execute_transaction(vec![]).success);
+    /// assert_eq!(world.borrow().balance(&config.transaction.from), evm::Word::from(3));
+    /// assert_eq!(world.borrow().balance(&[0x11; 20]), evm::Word::from(5));
+    /// ```
+    pub fn execute_transaction(&self, code: Vec<u8>) -> EvmResult {
+        let creation = self.config.transaction.to == [0u8; 20];
+        let calldata_too_large = !creation
+            && self
+                .config
+                .max_calldata_bytes
+                .is_some_and(|max| self.config.transaction.data.len() > max);
+        let initcode_too_large = creation
+            && code.len() > crate::state::MAX_INITCODE_SIZE
+            && self.config.is_eip_enabled(3860, Hardfork::Shanghai);
+        let mut config = self.config.clone();
+        let (sender, value) = (config.transaction.from, config.transaction.value);
+        let nonce = config
+            .test_state
+            .as_ref()
+            .map(|world| world.borrow_mut().increment_nonce(&sender))
+            .unwrap_or_default();
+        let intrinsic_gas = if creation {
+            config.transaction.to = crate::util::create_address(&sender, nonce);
+            config.transaction.data = Vec::new();
+            crate::gas::intrinsic_gas(&code) + crate::gas::GAS_CREATE
+        } else {
+            crate::gas::intrinsic_gas(&config.transaction.data)
+        };
+
+        let mut state = EvmState::new(code, config);
+        let recipient = state.address;
+        let admitted = if calldata_too_large {
+            Err(EvmError::CalldataTooLarge)
+        } else if initcode_too_large {
+            Err(EvmError::InitcodeSizeExceeded)
+        } else {
+            state.gas_tracker.consume(intrinsic_gas)
+        }
+        .and_then(|()| {
+            // Journaled by the frame, so a failure gives the value back
+            if state.transfer(sender, recipient, value) { Ok(()) } else { Err(EvmError::InsufficientBalance) }
+        });
+        match admitted {
+            Ok(()) => state.run(),
+            Err(error) => state.abort(error),
+        }
+
+        // Code beyond the size limit fails the creation and burns all of its gas
+        if creation && !state.reverted && crate::state::exceeds_code_size(&self.config, &state.output) {
+            state.gas_tracker.consume_all();
+            state.abort(EvmError::CodeSizeExceeded);
+        }
+        self.commit(&state);
+
+        let mut result = state.result();
+        if creation && result.success {
+            if let Some(ref world) = self.config.test_state {
+                world.borrow_mut().set_code(&state.address, &result.return_data);
+            }
+            result.created_address = Some(state.address);
+            result.deployed_code = Some(result.return_data.clone());
+        }
+        result
+    }
+
+    /// Execute EVM bytecode as a call to the function with `selector`, passing `args`
+    ///
+    /// The calldata is built with `util::encode_call` and replaces the configured one.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{Evm, Word};
+    ///
+    /// // PUSH1 0 CALLDATALOAD PUSH1 0xe0 SHR PUSH1 4 CALLDATALOAD PUSH1 0x24 CALLDATALOAD
+    /// let code = vec![0x60, 0x00, 0x35, 0x60, 0xe0, 0x1c, 0x60, 0x04, 0x35, 0x60, 0x24, 0x35];
+    /// let result = Evm::default().call_function(code, [0x12, 0x34, 0x56, 0x78], &[Word::from(7), Word::from(8)]);
+    /// assert_eq!(result.stack, vec![Word::from(8), Word::from(7), Word::from(0x12345678)]);
+    /// ```
+    pub fn call_function(&self, code: Vec<u8>, selector: [u8; 4], args: &[Word]) -> EvmResult {
+        let mut config = self.config.clone();
+        config.transaction.data = crate::util::encode_call(selector, args);
+        Evm::new(config).execute(code)
+    }
+
+    /// Execute EVM bytecode and return the final stack as hex strings, top first
+    ///
+    /// # Example
+    /// ```
+    /// use evm::Evm;
+    ///
+    /// // PUSH1 0x01 PUSH1 0x02
+    /// assert_eq!(Evm::default().execute_to_stack(vec![0x60, 0x01, 0x60, 0x02]), vec!["0x2", "0x1"]);
+    /// ```
+    pub fn execute_to_stack(&self, code: Vec<u8>) -> Vec<String> {
+        self.execute(code)
+            .stack
+            .iter()
+            .map(|value| format!("{:#x}", value))
+            .collect()
+    }
+
+    /// Execute EVM bytecode and also return the final state for inspection
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{Evm, Word};
+    ///
+    /// // PUSH1 0x2a PUSH1 0 MSTORE
+    /// let (result, state) = Evm::default().execute_debug(vec![0x60, 0x2a, 0x60, 0x00, 0x52]);
+    /// assert!(result.success);
+    /// assert_eq!(state.program_counter, 5);
+    /// assert_eq!(state.memory.peek(31, 1), vec![0x2a]);
+    /// ```
+    pub fn execute_debug(&self, code: Vec<u8>) -> (EvmResult, EvmState) {
         let mut state = EvmState::new(code, self.config.clone()); //todo could be a problem here
         
         // Execute until halted or error
-        while state.status() == crate::state::ExecutionStatus::Running {
-            if let Err(_) = state.step() {
-                // On error, execution stops and returns failure
-                state.reverted = true;
+        state.run();
+        self.commit(&state);
+        
+        (state.result(), state)
+    }
+
+    /// Execute EVM bytecode and record an EIP-3155 trace of every step
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{Evm, Word};
+    ///
+    /// // PUSH1 1 PUSH1 2 ADD
+    /// let (result, trace) = Evm::default().execute_with_trace(vec![0x60, 0x01, 0x60, 0x02, 0x01]);
+    /// assert!(result.success);
+    /// assert_eq!(trace.len(), 3);
+    ///
+    /// assert_eq!(trace[2].pc, 4);
+    /// assert_eq!(trace[2].op, 0x01);
+    /// assert_eq!(trace[2].gas, 30_000_000 - 6);
+    /// assert_eq!(trace[2].gas_cost, 3);
+    /// assert_eq!(trace[2].stack, vec![Word::from(1), Word::from(2)]);
+    /// assert_eq!(trace[2].depth, 1);
+    /// assert_eq!(trace[2].mem_size, 0);
+    /// assert_eq!(
+    ///     trace[2].to_json_line(),
+    ///     r#"{"pc":4,"op":1,"gas":"0x1c9c37a","gasCost":"0x3","stack":["0x1","0x2"],"depth":1,"memSize":0}"#
+    /// );
+    ///
+    /// // PUSH1 1 PUSH1 0 SSTORE - the step's cost includes setting a fresh slot,
+    /// // which SSTORE charges while it runs
+    /// let (result, trace) = Evm::default().execute_with_trace(vec![0x60, 0x01, 0x60, 0x00, 0x55]);
+    /// assert!(trace[2].gas_cost > 20000);
+    /// assert_eq!(trace[2].gas_cost, result.gas_used - 3 - 3);
+    /// ```
+    pub fn execute_with_trace(&self, code: Vec<u8>) -> (EvmResult, Vec<TraceStep>) {
+        let mut state = EvmState::new(code, self.config.clone());
+        let mut trace = Vec::new();
+
+        while state.status() == ExecutionStatus::Running {
+            if state.program_counter >= state.code.len() {
+                state.halted = true;
+                break;
+            }
+
+            let mut step = TraceStep::capture(&state);
+            let outcome = state.step();
+            step.finish(&state);
+            trace.push(step);
+
+            if let Err(error) = outcome {
+                state.abort(error);
                 break;
             }
         }
-        
-        state.result()
+        self.commit(&state);
+
+        (state.result(), trace)
+    }
+
+    /// Run `code` and compare every step against a geth struct log
+    ///
+    /// The pc, opcode, remaining gas and stack of each step must match the
+    /// corresponding struct-log entry; the first difference is returned.
+    ///
+    /// # Example
+    /// ```
+    /// use evm::Evm;
+    /// use evm::trace::Divergence;
+    ///
+    /// // PUSH1 1 PUSH1 2 ADD
+    /// let code = vec![0x60, 0x01, 0x60, 0x02, 0x01];
+    /// let geth = r#"{"structLogs": [
+    ///     {"pc": 0, "op": "PUSH1", "gas": 30000000, "gasCost": 3, "depth": 1, "stack": []},
+    ///     {"pc": 2, "op": "PUSH1", "gas": 29999997, "gasCost": 3, "depth": 1, "stack": ["0x1"]},
+    ///     {"pc": 4, "op": "ADD", "gas": 29999994, "gasCost": 3, "depth": 1, "stack": ["0x1", "0x2"]}
+    /// ]}"#;
+    /// assert_eq!(Evm::default().verify_against_trace(code.clone(), geth), Ok(()));
+    ///
+    /// // The same trace with a wrong stack on the last step
+    /// let diverged = geth.replace(r#"["0x1", "0x2"]"#, r#"["0x1", "0x3"]"#);
+    /// match Evm::default().verify_against_trace(code, &diverged) {
+    ///     Err(Divergence::Step { step, field, .. }) => assert_eq!((step, field), (2, "stack")),
+    ///     other => panic!("unexpected {:?}", other),
+    /// }
+    /// ```
+    pub fn verify_against_trace(&self, code: Vec<u8>, geth_struct_log: &str) -> Result<(), Divergence> {
+        let reference = crate::trace::parse_struct_log(geth_struct_log).map_err(Divergence::InvalidTrace)?;
+        let (_, trace) = self.execute_with_trace(code);
+        crate::trace::compare(&trace, &reference)
+    }
+
+    /// Persist storage changes of a successful execution to the world state
+    ///
+    /// A reverted execution has already put the world state back as it found
+    /// it, nested calls included, so there is nothing to persist.
+    fn commit(&self, state: &EvmState) {
+        if let Some(ref test_state) = self.config.test_state {
+            if !state.reverted {
+                let mut world = test_state.borrow_mut();
+                for (address, slots) in &state.storage {
+                    world.set_storage(address, slots);
+                }
+            }
+        }
     }
 
     /// Get the current configuration
@@ -58,11 +383,174 @@ impl EvmBuilder {
         }
     }
 
+    /// Set the gas available to the execution
+    ///
+    /// # Example
+    /// ```
+    /// use evm::EvmBuilder;
+    ///
+    /// // PUSH1 0 PUSH1 0 PUSH1 0 CREATE - the 32000 base cost is charged
+    /// // before the (empty) initcode gets any gas
+    /// let code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0];
+    ///
+    /// let result = EvmBuilder::new().gas_limit(32_008).build().execute(code.clone());
+    /// assert!(!result.success);
+    ///
+    /// let result = EvmBuilder::new().gas_limit(32_009).build().execute(code);
+    /// assert!(result.success);
+    /// assert_eq!(result.gas_used, 32_009);
+    /// ```
     pub fn gas_limit(mut self, gas_limit: Gas) -> Self {
         self.config.gas_limit = gas_limit;
         self
     }
 
+    /// Seed the gas refund counter, e.g. with refunds accrued before a checkpoint
+    ///
+    /// # Example
+    /// ```
+    /// use evm::EvmBuilder;
+    ///
+    /// // SSTORE slot 0 = 1, SSTORE slot 1 = 1, then clear slot 0 (refunds 15000)
+    /// let code = vec![
+    ///     0x60, 0x01, 0x60, 0x00, 0x55,
+    ///     0x60, 0x01, 0x60, 0x01, 0x55,
+    ///     0x60, 0x00, 0x60, 0x00, 0x55,
+    /// ];
+    ///
+    /// let result = EvmBuilder::new().gas_refund(1000).build().execute(code.clone());
+    /// assert_eq!(result.gas_refund, 16000);
+    ///
+    /// // The refund is capped at half of the gas used
+    /// let result = EvmBuilder::new().gas_refund(30000).build().execute(code);
+    /// assert_eq!(result.gas_refund, result.gas_used / 2);
+    /// ```
+    pub fn gas_refund(mut self, gas_refund: Gas) -> Self {
+        self.config.initial_gas_refund = gas_refund;
+        self
+    }
+
+    /// Cap the memory size of each execution context
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, EvmError};
+    /// use evm::types::Outcome;
+    ///
+    /// // PUSH1 1 PUSH6 0x010000000000 MSTORE - a write at offset 2^40
+    /// let code = vec![0x60, 0x01, 0x65, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x52];
+    /// let result = EvmBuilder::new().max_memory_bytes(1024 * 1024).build().execute(code);
+    /// assert_eq!(result.outcome, Outcome::Halted(EvmError::MemoryOutOfBounds));
+    /// ```
+    pub fn max_memory_bytes(mut self, max_memory_bytes: usize) -> Self {
+        self.config.max_memory_bytes = max_memory_bytes;
+        self
+    }
+
+    /// Count executions and gas per opcode, returned in `EvmResult::gas_profile`
+    ///
+    /// # Example
+    /// ```
+    /// use evm::EvmBuilder;
+    /// use evm::opcodes::Opcode;
+    ///
+    /// // Count from 0 to 3:
+    /// //     PUSH1 0
+    /// // 2:  JUMPDEST PUSH1 1 ADD DUP1 PUSH1 3 GT PUSH1 14 JUMPI STOP
+    /// // 14: JUMPDEST PUSH1 2 JUMP
+    /// let code = vec![
+    ///     0x60, 0x00, 0x5b, 0x60, 0x01, 0x01, 0x80, 0x60, 0x03, 0x11, 0x60, 0x0e, 0x57, 0x00,
+    ///     0x5b, 0x60, 0x02, 0x56,
+    /// ];
+    /// let result = EvmBuilder::new().gas_profiling(true).build().execute(code.clone());
+    /// let profile = result.gas_profile.unwrap();
+    /// assert_eq!(profile[&Opcode::Add], (3, 9));
+    /// assert_eq!(profile[&Opcode::Jumpi].0, 3);
+    /// assert_eq!(profile[&Opcode::Jump].0, 2);
+    /// assert_eq!(profile.values().map(|(_, gas)| gas).sum::<u64>(), result.gas_used);
+    ///
+    /// // Off by default
+    /// assert!(EvmBuilder::new().build().execute(code).gas_profile.is_none());
+    /// ```
+    pub fn gas_profiling(mut self, enabled: bool) -> Self {
+        self.config.gas_profiling = enabled;
+        self
+    }
+
+    /// Abort execution after `max_steps` opcodes, to bound runaway programs
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, EvmError};
+    /// use evm::types::Outcome;
+    ///
+    /// // JUMPDEST PUSH1 0 JUMP - loops until gas runs out
+    /// let code = vec![0x5b, 0x60, 0x00, 0x56];
+    /// let (result, state) = EvmBuilder::new().max_steps(1000).build().execute_debug(code);
+    /// assert!(!result.success);
+    /// assert_eq!(result.outcome, Outcome::Halted(EvmError::StepLimitExceeded));
+    /// assert_eq!(state.steps, 1000);
+    /// ```
+    pub fn max_steps(mut self, max_steps: u64) -> Self {
+        self.config.max_steps = Some(max_steps);
+        self
+    }
+
+    /// Reject transactions carrying more than `max_calldata_bytes` of calldata
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, EvmError};
+    /// use evm::types::Outcome;
+    ///
+    /// let mut evm = EvmBuilder::new().max_calldata_bytes(4).build();
+    /// let mut config = evm.config().clone();
+    /// config.transaction.to = [0x11; 20];
+    /// config.transaction.data = vec![0xff; 4];
+    /// evm.update_config(config.clone());
+    /// assert!(evm.execute_transaction(vec![]).success);
+    ///
+    /// config.transaction.data.push(0xff);
+    /// evm.update_config(config);
+    /// let result = evm.execute_transaction(vec![]);
+    /// assert_eq!(result.outcome, Outcome::Halted(EvmError::CalldataTooLarge));
+    /// ```
+    pub fn max_calldata_bytes(mut self, max_calldata_bytes: usize) -> Self {
+        self.config.max_calldata_bytes = Some(max_calldata_bytes);
+        self
+    }
+
+    /// Start execution in a static context, as the callee of a STATICCALL
+    ///
+    /// # Example
+    /// ```
+    /// use evm::EvmBuilder;
+    ///
+    /// // PUSH1 1 PUSH1 0 SSTORE
+    /// let code = vec![0x60, 0x01, 0x60, 0x00, 0x55];
+    /// assert!(EvmBuilder::new().build().execute(code.clone()).success);
+    /// assert!(!EvmBuilder::new().static_context(true).build().execute(code).success);
+    /// ```
+    pub fn static_context(mut self, static_context: bool) -> Self {
+        self.config.static_context = static_context;
+        self
+    }
+
+    /// Set the transaction's gas price, which GASPRICE returns
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, Word};
+    ///
+    /// // GASPRICE
+    /// let evm = EvmBuilder::new().gas_price(Word::from(7_000_000_000u64)).build();
+    /// assert_eq!(evm.execute(vec![0x3a]).stack, vec![Word::from(7_000_000_000u64)]);
+    /// ```
+    pub fn gas_price(mut self, gas_price: Word) -> Self {
+        self.config.transaction.gas_price = gas_price;
+        self
+    }
+
     pub fn block_number(mut self, block_number: u64) -> Self {
         self.config.block_number = block_number;
         self
@@ -78,6 +566,169 @@ impl EvmBuilder {
         self
     }
 
+    /// Set the PREVRANDAO value returned by opcode 0x44 from Paris (the Merge) onwards
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, Hardfork, Word};
+    ///
+    /// let evm = |hardfork| EvmBuilder::new()
+    ///     .hardfork(hardfork)
+    ///     .block_difficulty(Word::from(0x20000))
+    ///     .block_prevrandao(Word::from(0xabcdef))
+    ///     .build();
+    ///
+    /// // 0x44 is PREVRANDAO post-Merge and DIFFICULTY before it
+    /// assert_eq!(evm(Hardfork::Shanghai).execute(vec![0x44]).stack, vec![Word::from(0xabcdef)]);
+    /// assert_eq!(evm(Hardfork::London).execute(vec![0x44]).stack, vec![Word::from(0x20000)]);
+    /// ```
+    pub fn block_prevrandao(mut self, block_prevrandao: Word) -> Self {
+        self.config.block_prevrandao = block_prevrandao;
+        self
+    }
+
+    /// Record the hash of a past block for BLOCKHASH
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, Word};
+    ///
+    /// let evm = EvmBuilder::new()
+    ///     .block_number(300)
+    ///     .block_hash(299, Word::from(0xaa))
+    ///     .block_hash(44, Word::from(0xbb))
+    ///     .block_hash(43, Word::from(0xcc))
+    ///     .block_hash(300, Word::from(0xdd))
+    ///     .build();
+    /// let blockhash = |number: u16| evm.execute(vec![0x61, (number >> 8) as u8, number as u8, 0x40]).stack;
+    ///
+    /// // In the window of the last 256 blocks
+    /// assert_eq!(blockhash(299), vec![Word::from(0xaa)]);
+    /// assert_eq!(blockhash(44), vec![Word::from(0xbb)]);
+    /// // In the window but unknown
+    /// assert_eq!(blockhash(100), vec![Word::zero()]);
+    /// // Too old, current and future blocks
+    /// assert_eq!(blockhash(43), vec![Word::zero()]);
+    /// assert_eq!(blockhash(300), vec![Word::zero()]);
+    /// assert_eq!(blockhash(301), vec![Word::zero()]);
+    /// ```
+    pub fn block_hash(mut self, number: u64, hash: Word) -> Self {
+        self.config.block_hashes.insert(number, hash);
+        self
+    }
+
+    /// Set the excess blob gas, and the blob base fee derived from it
+    pub fn excess_blob_gas(mut self, excess_blob_gas: u64) -> Self {
+        self.config.excess_blob_gas = excess_blob_gas;
+        self.config.block_blob_base_fee = self.config.blob_base_fee();
+        self
+    }
+
+    /// Set the blob base fee returned by BLOBBASEFEE directly
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, Word};
+    ///
+    /// let result = EvmBuilder::new().block_blob_base_fee(Word::from(0x1234)).build().execute(vec![0x4a]);
+    /// assert_eq!(result.stack, vec![Word::from(0x1234)]);
+    ///
+    /// // Or derived from the excess blob gas
+    /// let result = EvmBuilder::new().excess_blob_gas(10 * 3338477).build().execute(vec![0x4a]);
+    /// assert_eq!(result.stack, vec![Word::from(22026)]);
+    /// ```
+    pub fn block_blob_base_fee(mut self, block_blob_base_fee: Word) -> Self {
+        self.config.block_blob_base_fee = block_blob_base_fee;
+        self
+    }
+
+    /// Set the versioned hashes of the transaction's blobs
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, Word};
+    ///
+    /// let evm = EvmBuilder::new()
+    ///     .blob_versioned_hashes(vec![Word::from(0x0111), Word::from(0x0122)])
+    ///     .build();
+    /// let blobhash = |index: u8| evm.execute(vec![0x60, index, 0x49]).stack;
+    ///
+    /// assert_eq!(blobhash(0), vec![Word::from(0x0111)]);
+    /// assert_eq!(blobhash(1), vec![Word::from(0x0122)]);
+    /// assert_eq!(blobhash(2), vec![Word::zero()]);
+    /// assert_eq!(blobhash(0xff), vec![Word::zero()]);
+    /// ```
+    pub fn blob_versioned_hashes(mut self, blob_versioned_hashes: Vec<Word>) -> Self {
+        self.config.blob_versioned_hashes = blob_versioned_hashes;
+        self
+    }
+
+    /// Pre-warm addresses and storage slots, as an EIP-2930 transaction declares them
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, Word};
+    ///
+    /// // PUSH1 1 SLOAD PUSH1 1 SLOAD - the first access is cold, the second warm
+    /// let code = vec![0x60, 0x01, 0x54, 0x60, 0x01, 0x54];
+    /// let result = EvmBuilder::new().build().execute(code.clone());
+    /// assert_eq!(result.gas_used, 3 + 2100 + 3 + 100);
+    ///
+    /// // With slot 1 of the executing account in the access list, both are warm
+    /// let evm = EvmBuilder::new().access_list(vec![([0u8; 20], vec![Word::one()])]).build();
+    /// assert_eq!(evm.execute(code).gas_used, 3 + 100 + 3 + 100);
+    ///
+    /// // PUSH20 0xbeef BALANCE - a listed address is warm too
+    /// let mut code = vec![0x73];
+    /// code.extend_from_slice(&[0u8; 18]);
+    /// code.extend_from_slice(&[0xbe, 0xef]);
+    /// code.push(0x31);
+    /// assert_eq!(EvmBuilder::new().build().execute(code.clone()).gas_used, 3 + 2600);
+    /// let mut beef = [0u8; 20];
+    /// beef[18..].copy_from_slice(&[0xbe, 0xef]);
+    /// let evm = EvmBuilder::new().access_list(vec![(beef, vec![])]).build();
+    /// assert_eq!(evm.execute(code).gas_used, 3 + 100);
+    /// ```
+    pub fn access_list(mut self, access_list: Vec<(Address, Vec<Word>)>) -> Self {
+        self.config.access_list = access_list;
+        self
+    }
+
+    /// Seed the storage of the executing contract, the transaction's `to` account
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashMap;
+    /// use evm::{EvmBuilder, Word};
+    ///
+    /// let storage = HashMap::from([(Word::one(), Word::from(0x2a))]);
+    /// let evm = EvmBuilder::new().storage(storage).build();
+    ///
+    /// // PUSH1 1 SLOAD PUSH1 2 SLOAD
+    /// let result = evm.execute(vec![0x60, 0x01, 0x54, 0x60, 0x02, 0x54]);
+    /// assert_eq!(result.stack, vec![Word::zero(), Word::from(0x2a)]);
+    /// ```
+    pub fn storage(self, storage: std::collections::HashMap<Word, Word>) -> Self {
+        if let Some(ref world) = self.config.test_state {
+            world.borrow_mut().set_storage(&self.config.transaction.to, &storage);
+        }
+        self
+    }
+
+    /// Set the transaction origin returned by ORIGIN
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, Word};
+    ///
+    /// let result = EvmBuilder::new().origin([0xee; 20]).build().execute(vec![0x32]);
+    /// assert_eq!(result.stack, vec![Word::from_big_endian(&[0xee; 20])]);
+    /// ```
+    pub fn origin(mut self, origin: Address) -> Self {
+        self.config.origin = origin;
+        self
+    }
+
     pub fn block_gas_limit(mut self, block_gas_limit: Gas) -> Self {
         self.config.block_gas_limit = U256::from(block_gas_limit);
         self
@@ -88,6 +739,48 @@ impl EvmBuilder {
         self
     }
 
+    /// Select the hardfork whose rules apply; opcodes introduced later are invalid
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, Hardfork, Word};
+    ///
+    /// // PUSH0 is only available from Shanghai onwards
+    /// let result = EvmBuilder::new().hardfork(Hardfork::London).build().execute(vec![0x5f]);
+    /// assert!(!result.success);
+    ///
+    /// let result = EvmBuilder::new().hardfork(Hardfork::Shanghai).build().execute(vec![0x5f]);
+    /// assert!(result.success);
+    /// assert_eq!(result.stack, vec![Word::zero()]);
+    /// ```
+    pub fn hardfork(mut self, hardfork: Hardfork) -> Self {
+        self.config.hardfork = hardfork;
+        self
+    }
+
+    /// Enable a single EIP on top of the selected hardfork
+    ///
+    /// # Example
+    /// ```
+    /// use evm::{EvmBuilder, Hardfork};
+    ///
+    /// // PUSH1 32 PUSH0 PUSH0 CREATE - deploys from 32 zero bytes of initcode
+    /// let code = vec![0x60, 0x20, 0x5f, 0x5f, 0xf0];
+    ///
+    /// // London with PUSH0 (EIP-3855): PUSH0 works, but there is no initcode word cost (EIP-3860)
+    /// let london = EvmBuilder::new().hardfork(Hardfork::London).enable_eip(3855).build();
+    /// let result = london.execute(code.clone());
+    /// assert!(result.success);
+    ///
+    /// // Shanghai charges 2 gas for the one word of initcode on top
+    /// let shanghai = EvmBuilder::new().hardfork(Hardfork::Shanghai).build();
+    /// assert_eq!(shanghai.execute(code).gas_used, result.gas_used + 2);
+    /// ```
+    pub fn enable_eip(mut self, eip: u32) -> Self {
+        self.config.enabled_eips.insert(eip);
+        self
+    }
+
     pub fn build(self) -> Evm {
         Evm::new(self.config)
     }
@@ -0,0 +1,17 @@
+//! A normal run writes nothing to stdout beyond the harness's own report
+
+use std::process::Command;
+
+#[test]
+fn harness_prints_only_its_report() {
+    let tests = concat!(env!("CARGO_MANIFEST_DIR"), "/../evm.json");
+    let output = Command::new(env!("CARGO_BIN_EXE_evm")).arg(tests).output().unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let unexpected: Vec<&str> = stdout
+        .lines()
+        .filter(|line| !(line.starts_with("Test ") || *line == "PASS" || line.starts_with("Passed: ") || *line == "Congratulations!"))
+        .collect();
+    assert!(unexpected.is_empty(), "unexpected output: {:?}", unexpected);
+}